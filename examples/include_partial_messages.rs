@@ -1,40 +1,17 @@
-use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio;
-use tokio::sync::Mutex;
-
-use claude_agent_sdk::transport::Transport;
-use claude_agent_sdk::types::{
-    AssistantMessage, Message, ResultMessage, StreamEvent, SystemMessage, UserMessage,
-};
-use claude_agent_sdk::{Client, SubprocessCLITransport};
-
-// Define ClaudeAgentOptions struct
-#[derive(Debug, Clone)]
-struct ClaudeAgentOptions {
-    include_partial_messages: Option<bool>,
-    model: Option<String>,
-    max_turns: Option<u32>,
-    env: Option<HashMap<String, String>>,
-}
+use claude_agent_sdk::partial::{Delta, MessageAccumulator, MessageUpdate};
+use claude_agent_sdk::types::{ClaudeAgentOptions, ContentBlock, Message};
+use claude_agent_sdk::{query, AgentError};
+use futures::StreamExt;
+use std::io::Write;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> Result<(), AgentError> {
     // Enable partial message streaming
-    let mut env = HashMap::new();
-    env.insert("MAX_THINKING_TOKENS".to_string(), "8000".to_string());
-
-    let options = ClaudeAgentOptions {
-        include_partial_messages: Some(true),
-        model: Some("claude-sonnet-4-5".to_string()),
-        max_turns: Some(2),
-        env: Some(env),
-    };
-
-    // Create client
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
+    let options = ClaudeAgentOptions::new()
+        .include_partial_messages(true)
+        .model("claude-sonnet-4-5")
+        .max_turns(2)
+        .env("MAX_THINKING_TOKENS", "8000");
 
     // Send a prompt that will generate a streaming response
     // prompt = "Run a bash command to sleep for 5 seconds"
@@ -42,18 +19,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     println!("Prompt: {}\n", prompt);
     println!("==================================================");
 
-    let user_message = UserMessage {
-        content: prompt.to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-
-    // In a real implementation, we would send the query and receive responses
-    // client.query(prompt).await?;
-    // while let Ok(message) = client.get_next_message().await {
-    //     println!("{:?}", message);
-    // }
-    println!("Would send query with client and receive streaming responses");
+    let mut accumulator = MessageAccumulator::new();
+    let mut messages = query(prompt, options).await?;
+    while let Some(message) = messages.next().await {
+        let message = message?;
+        match accumulator.push_message(&message)? {
+            // Print text as it is written
+            Some(MessageUpdate::BlockDelta {
+                delta: Delta::Text(text) | Delta::Thinking(text),
+                ..
+            }) => {
+                print!("{}", text);
+                std::io::stdout().flush()?;
+            }
+            Some(MessageUpdate::BlockStarted { index }) => {
+                if let Some(ContentBlock::Thinking(_)) = accumulator
+                    .message()
+                    .and_then(|message| message.content.get(index))
+                {
+                    println!("[thinking]");
+                }
+            }
+            Some(MessageUpdate::BlockCompleted { block, .. }) => match block {
+                ContentBlock::ToolUse(tool_use) => {
+                    println!("[tool use] {} {:?}", tool_use.name, tool_use.input)
+                }
+                _ => println!(),
+            },
+            Some(MessageUpdate::MessageCompleted { stop_reason, usage }) => {
+                println!(
+                    "[stop reason: {}, output tokens: {}]",
+                    stop_reason.unwrap_or_default(),
+                    usage.map(|usage| usage.output_tokens).unwrap_or_default()
+                );
+            }
+            _ => {}
+        }
+        if let Message::Result(result) = message {
+            if let Some(cost) = result.total_cost_usd {
+                println!("\nCost: ${:.4}", cost);
+            }
+        }
+    }
 
     Ok(())
 }
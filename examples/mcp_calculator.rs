@@ -1,14 +1,15 @@
-use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use futures::StreamExt;
+use serde::Deserialize;
 
-use claude_agent_sdk::types::{ContentBlock, Message, UserMessage};
-use claude_agent_sdk::{Client, SubprocessCLITransport};
+use claude_agent_sdk::mcp::{SdkMcpServer, SdkMcpTool, ToolResult};
+use claude_agent_sdk::schema::InputSchema;
+use claude_agent_sdk::types::{ClaudeAgentOptions, ContentBlock, Message};
+use claude_agent_sdk::ClaudeSDKClient;
 
 fn display_message(msg: Message) {
     match msg {
         Message::User(user_msg) => {
-            println!("User: {}", user_msg.content);
+            println!("User: {}", user_msg.content.text());
         }
         Message::Assistant(assistant_msg) => {
             for block in assistant_msg.content {
@@ -36,6 +37,76 @@ fn display_message(msg: Message) {
     }
 }
 
+/// Two operands for a binary operation.
+#[derive(Deserialize, InputSchema)]
+struct Operands {
+    /// The left-hand operand
+    a: f64,
+    /// The right-hand operand
+    b: f64,
+}
+
+#[derive(Deserialize, InputSchema)]
+struct SqrtInput {
+    /// The number to take the square root of
+    n: f64,
+}
+
+#[derive(Deserialize, InputSchema)]
+struct PowerInput {
+    base: f64,
+    exponent: f64,
+}
+
+fn calculator_server() -> SdkMcpServer {
+    SdkMcpServer::new("calc")
+        .tool(SdkMcpTool::typed(
+            "add",
+            "Add two numbers",
+            |Operands { a, b }| async move { Ok(ToolResult::text((a + b).to_string())) },
+        ))
+        .tool(SdkMcpTool::typed(
+            "subtract",
+            "Subtract one number from another",
+            |Operands { a, b }| async move { Ok(ToolResult::text((a - b).to_string())) },
+        ))
+        .tool(SdkMcpTool::typed(
+            "multiply",
+            "Multiply two numbers",
+            |Operands { a, b }| async move { Ok(ToolResult::text((a * b).to_string())) },
+        ))
+        .tool(SdkMcpTool::typed(
+            "divide",
+            "Divide one number by another",
+            |Operands { a, b }| async move {
+                if b == 0.0 {
+                    return Ok(ToolResult::error("Error: Division by zero is not allowed"));
+                }
+                Ok(ToolResult::text((a / b).to_string()))
+            },
+        ))
+        .tool(SdkMcpTool::typed(
+            "sqrt",
+            "Calculate square root",
+            |SqrtInput { n }| async move {
+                if n < 0.0 {
+                    return Ok(ToolResult::error(format!(
+                        "Error: Cannot calculate square root of negative number {}",
+                        n
+                    )));
+                }
+                Ok(ToolResult::text(n.sqrt().to_string()))
+            },
+        ))
+        .tool(SdkMcpTool::typed(
+            "power",
+            "Raise a number to a power",
+            |PowerInput { base, exponent }| async move {
+                Ok(ToolResult::text(base.powf(exponent).to_string()))
+            },
+        ))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Example prompts to demonstrate calculator usage
@@ -47,129 +118,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         "Calculate (12 + 8) * 3 - 10", // Complex calculation
     ];
 
-    let transport = SubprocessCLITransport::new("claude", &[]).map_err(|e| {
-        eprintln!("Error initializing transport: {}", e);
+    // The tools run in this process; the CLI only needs to know the server exists
+    let calculator = calculator_server();
+    let allowed_tools: Vec<String> = calculator
+        .tools()
+        .iter()
+        .map(|tool| format!("mcp__calc__{}", tool.name))
+        .collect();
+    let options = ClaudeAgentOptions::new()
+        .mcp_server("calc", calculator)
+        .allowed_tools(allowed_tools);
+
+    // Registering the server in the options is all the client needs to answer its tool calls
+    let mut client = ClaudeSDKClient::with_options(options);
+    client.connect(None).await.map_err(|e| {
+        eprintln!("Error connecting to Claude: {}", e);
         eprintln!("Make sure the Claude CLI is installed and accessible in your PATH");
         e
     })?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    // Create tool definitions for the client
-    let tools = Some(vec![
-        json!({
-            "name": "add",
-            "description": "Add two numbers",
-            "input_schema": {
-                "type": "object",
-                "properties": {
-                    "a": {"type": "number"},
-                    "b": {"type": "number"}
-                },
-                "required": ["a", "b"]
-            }
-        }),
-        json!({
-            "name": "subtract",
-            "description": "Subtract one number from another",
-            "input_schema": {
-                "type": "object",
-                "properties": {
-                    "a": {"type": "number"},
-                    "b": {"type": "number"}
-                },
-                "required": ["a", "b"]
-            }
-        }),
-        json!({
-            "name": "multiply",
-            "description": "Multiply two numbers",
-            "input_schema": {
-                "type": "object",
-                "properties": {
-                    "a": {"type": "number"},
-                    "b": {"type": "number"}
-                },
-                "required": ["a", "b"]
-            }
-        }),
-        json!({
-            "name": "divide",
-            "description": "Divide one number by another",
-            "input_schema": {
-                "type": "object",
-                "properties": {
-                    "a": {"type": "number"},
-                    "b": {"type": "number"}
-                },
-                "required": ["a", "b"]
-            }
-        }),
-        json!({
-            "name": "sqrt",
-            "description": "Calculate square root",
-            "input_schema": {
-                "type": "object",
-                "properties": {
-                    "n": {"type": "number"}
-                },
-                "required": ["n"]
-            }
-        }),
-        json!({
-            "name": "power",
-            "description": "Raise a number to a power",
-            "input_schema": {
-                "type": "object",
-                "properties": {
-                    "base": {"type": "number"},
-                    "exponent": {"type": "number"}
-                },
-                "required": ["base", "exponent"]
-            }
-        }),
-    ]);
 
     for prompt in prompts {
         println!("\n==================================================");
         println!("Prompt: {}", prompt);
         println!("==================================================");
 
-        let user_message = UserMessage {
-            content: prompt.to_string(),
-            parent_tool_use_id: None,
-        };
-        let messages = vec![Message::User(user_message)];
-
-        // Send query and process responses
-        let _response = match client
-            .query(messages, tools.clone(), None, Some(true), None, None)
-            .await
-        {
-            Ok(response) => response,
-            Err(e) => {
-                eprintln!("Error sending query: {}", e);
-                continue;
-            }
-        };
-
-        // Process streaming messages
-        loop {
-            match client.get_next_message().await {
-                Ok(message) => {
-                    display_message(message.clone());
-
-                    // Check if this is a result message which indicates the end of the stream
-                    if let Message::Result(_) = message {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Error receiving message: {}", e);
-                    break;
-                }
-            }
+        client.query(prompt).await?;
+        let mut response = Box::pin(client.receive_response());
+        while let Some(message) = response.next().await {
+            display_message(message?);
         }
     }
 
+    client.disconnect().await?;
     Ok(())
 }
@@ -1,7 +1,4 @@
-use serde_json::Value;
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio;
 use tokio::sync::Mutex;
 
 use claude_agent_sdk::types::{Message, UserMessage};
@@ -14,10 +11,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let transport = SubprocessCLITransport::new("claude", &[])?;
     let client = Client::new(Arc::new(Mutex::new(transport)));
 
-    let user_message = UserMessage {
-        content: "What is 2+2?".to_string(),
-        parent_tool_use_id: None,
-    };
+    let user_message = UserMessage::text("What is 2+2?");
     let messages = vec![Message::User(user_message)];
 
     println!("Sending query...");
@@ -1,54 +1,66 @@
-use serde_json::Value;
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio;
-use tokio::sync::Mutex;
-
-use claude_agent_sdk::transport::Transport;
-use claude_agent_sdk::types::{
-    AssistantMessage, ContentBlock, Message, ResultMessage, SystemMessage, TextBlock, UserMessage,
-};
-use claude_agent_sdk::{Client, SubprocessCLITransport};
-
-// Define ClaudeAgentOptions struct
-#[derive(Debug, Clone)]
-struct ClaudeAgentOptions {
-    stderr: Option<fn(String)>, // Callback function for stderr
-    extra_args: Option<HashMap<String, Option<String>>>,
-}
+
+use futures::StreamExt;
+use tokio::sync::mpsc;
+
+use claude_agent_sdk::internal::client::InternalClient;
+use claude_agent_sdk::types::{ClaudeAgentOptions, Message, UserMessage};
+use claude_agent_sdk::SubprocessCLITransport;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Forward every stderr line into a channel so it can be handled elsewhere
+    let (stderr_tx, mut stderr_rx) = mpsc::unbounded_channel::<String>();
+
     // Create options with stderr callback and enable debug mode
-    let mut extra_args = HashMap::new();
-    extra_args.insert("debug-to-stderr".to_string(), None);
+    let options = ClaudeAgentOptions::new()
+        .extra_arg("debug-to-stderr", None)
+        .stderr(move |line| {
+            let _ = stderr_tx.send(line.to_string());
+        });
 
-    fn stderr_callback(message: String) {
-        // Optionally print specific messages
-        if message.contains("[ERROR]") {
-            println!("Error detected: {}", message);
+    let collector = tokio::spawn(async move {
+        let mut lines = Vec::new();
+        while let Some(line) = stderr_rx.recv().await {
+            // Optionally print specific messages
+            if line.contains("[ERROR]") {
+                println!("Error detected: {}", line);
+            }
+            lines.push(line);
         }
-    }
-
-    let options = ClaudeAgentOptions {
-        stderr: Some(stderr_callback),
-        extra_args: Some(extra_args),
-    };
+        lines
+    });
 
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    // Run a query
+    // Run a query; this is what `query()` does, keeping a handle on the
+    // transport so its stderr buffer can be read afterwards
     println!("Running query with stderr capture...");
+    let transport = Arc::new(SubprocessCLITransport::from_options_async(&options).await?);
+    let mut messages = InternalClient::new()
+        .process_query(
+            UserMessage::text("What is 2+2?"),
+            &options,
+            transport.clone(),
+        )
+        .await?;
+    while let Some(message) = messages.next().await {
+        if let Message::Result(result) = message? {
+            println!("Response: {}", result.result.unwrap_or_default());
+        }
+    }
 
-    let user_message = UserMessage {
-        content: "What is 2+2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
+    // The callback's sender lives in the options; dropping them ends the collector
+    drop(messages);
+    drop(options);
+    let lines = collector.await?;
+    println!("\nCaptured {} stderr lines", lines.len());
+    if let Some(first) = lines.first() {
+        println!("First line: {}", first);
+    }
 
-    // In a real implementation, we would send the query and capture stderr
-    println!("Would send query and capture stderr output");
+    // The transport also keeps the most recent lines for error reports
+    for line in transport.recent_stderr() {
+        println!("Recent stderr: {}", line);
+    }
 
     Ok(())
 }
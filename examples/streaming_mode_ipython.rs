@@ -2,57 +2,56 @@
 // These examples are designed to be copy-pasted directly into IPython.
 // Each example is self-contained and can be run independently.
 
-use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio;
-use tokio::sync::Mutex;
-
-use claude_agent_sdk::transport::Transport;
-use claude_agent_sdk::types::{
-    AssistantMessage, ContentBlock, Message, ResultMessage, SystemMessage, TextBlock, UserMessage,
-};
-use claude_agent_sdk::{Client, SubprocessCLITransport};
+use claude_agent_sdk::types::{ContentBlock, Message};
+use claude_agent_sdk::{AgentError, ClaudeSDKClient};
+use futures::StreamExt;
+use std::io::Write;
 
 // BASIC STREAMING EXAMPLE
-async fn basic_streaming_example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
+async fn basic_streaming_example() -> Result<(), AgentError> {
+    let mut client = ClaudeSDKClient::new();
     println!("User: What is 2+2?");
+    client.connect(Some("What is 2+2?")).await?;
+
+    let mut response = Box::pin(client.receive_response());
+    while let Some(message) = response.next().await {
+        if let Message::Assistant(assistant_msg) = message? {
+            for block in assistant_msg.content {
+                if let ContentBlock::Text(text_block) = block {
+                    println!("Claude: {}", text_block.text);
+                }
+            }
+        }
+    }
+    drop(response);
 
-    let user_message = UserMessage {
-        content: "What is 2+2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-
-    // In a real implementation, we would send the query and receive responses
-    println!("Would send query and receive streaming responses");
-
-    Ok(())
+    client.disconnect().await
 }
 
 // STREAMING WITH REAL-TIME DISPLAY EXAMPLE
-async fn streaming_with_display_example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    async fn send_and_receive(
-        client: &Client,
-        prompt: &str,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        println!("User: {}", prompt);
-
-        let user_message = UserMessage {
-            content: prompt.to_string(),
-            parent_tool_use_id: None,
-        };
-        let messages = vec![Message::User(user_message)];
-
-        // In a real implementation, we would send the query and receive responses
-        println!("Would send query and receive response");
+async fn streaming_with_display_example() -> Result<(), AgentError> {
+    let mut client = ClaudeSDKClient::new();
+    client.connect(None).await?;
 
+    async fn send_and_receive(client: &ClaudeSDKClient, prompt: &str) -> Result<(), AgentError> {
+        println!("User: {}", prompt);
+        client.query(prompt).await?;
+
+        let mut response = Box::pin(client.receive_response());
+        while let Some(message) = response.next().await {
+            match message? {
+                Message::Assistant(assistant_msg) => {
+                    for block in assistant_msg.content {
+                        if let ContentBlock::Text(text_block) = block {
+                            print!("Claude: {}", text_block.text);
+                            std::io::stdout().flush()?;
+                        }
+                    }
+                }
+                Message::Result(_) => println!(),
+                _ => {}
+            }
+        }
         Ok(())
     }
 
@@ -60,35 +59,32 @@ async fn streaming_with_display_example() -> Result<(), Box<dyn std::error::Erro
     println!("\n---\n");
     send_and_receive(&client, "Now tell me a fun fact").await?;
 
-    Ok(())
+    client.disconnect().await
 }
 
 // PERSISTENT CLIENT EXAMPLE
-async fn persistent_client_example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    println!("User: What's 2+2?");
-    let user_message = UserMessage {
-        content: "What's 2+2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-    println!("Would send query and receive response");
-
-    println!("User: What's 10*10?");
-    let user_message = UserMessage {
-        content: "What's 10*10?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-    println!("Would send query and receive response");
+async fn persistent_client_example() -> Result<(), AgentError> {
+    let mut client = ClaudeSDKClient::new();
+    client.connect(None).await?;
 
-    Ok(())
+    // The same session answers every prompt, so earlier turns stay in context
+    for prompt in ["What's 2+2?", "What's 10*10?"] {
+        println!("User: {}", prompt);
+        client.query(prompt).await?;
+
+        let mut response = Box::pin(client.receive_response());
+        while let Some(message) = response.next().await {
+            if let Message::Result(result_msg) = message? {
+                println!("Claude: {}", result_msg.result.unwrap_or_default());
+            }
+        }
+    }
+
+    client.disconnect().await
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> Result<(), AgentError> {
     basic_streaming_example().await?;
     println!("\n");
 
@@ -1,99 +1,60 @@
 // Example demonstrating different system_prompt configurations.
 
-use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio;
-use tokio::sync::Mutex;
-
-use claude_agent_sdk::transport::Transport;
-use claude_agent_sdk::types::{
-    AssistantMessage, ContentBlock, Message, ResultMessage, SystemMessage, TextBlock, UserMessage,
-};
-use claude_agent_sdk::{Client, SubprocessCLITransport};
-
-async fn no_system_prompt() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    println!("=== No System Prompt (Vanilla Claude) ===");
-
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    let user_message = UserMessage {
-        content: "What is 2 + 2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-
-    // In a real implementation, we would send the query and receive responses
-    println!("Would send query: \"What is 2 + 2?\" and receive responses");
+use claude_agent_sdk::types::{ClaudeAgentOptions, ContentBlock, Message, SystemPrompt};
+use claude_agent_sdk::{query, AgentError};
+use futures::StreamExt;
+
+// Asks the same question under `options` and prints Claude's text
+async fn ask(options: ClaudeAgentOptions) -> Result<(), AgentError> {
+    let mut messages = query("What is 2 + 2?", options).await?;
+    while let Some(message) = messages.next().await {
+        if let Message::Assistant(assistant_msg) = message? {
+            for block in assistant_msg.content {
+                if let ContentBlock::Text(text_block) = block {
+                    println!("Claude: {}", text_block.text);
+                }
+            }
+        }
+    }
     println!();
-
     Ok(())
 }
 
-async fn string_system_prompt() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    println!("=== String System Prompt ===");
-
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    let user_message = UserMessage {
-        content: "What is 2 + 2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
+async fn no_system_prompt() -> Result<(), AgentError> {
+    println!("=== No System Prompt (Vanilla Claude) ===");
+    ask(ClaudeAgentOptions::new()).await
+}
 
-    // In a real implementation, we would send the query with a system prompt and receive responses
-    println!(
-        "Would send query: \"What is 2 + 2?\" with pirate system prompt and receive responses"
-    );
-    println!();
+async fn string_system_prompt() -> Result<(), AgentError> {
+    println!("=== String System Prompt ===");
 
-    Ok(())
+    let options = ClaudeAgentOptions::new()
+        .system_prompt("You are a pirate assistant. Respond in pirate speak.");
+    ask(options).await
 }
 
-async fn preset_system_prompt() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn preset_system_prompt() -> Result<(), AgentError> {
     println!("=== Preset System Prompt (Default) ===");
 
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    let user_message = UserMessage {
-        content: "What is 2 + 2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-
-    // In a real implementation, we would send the query with a preset system prompt and receive responses
-    println!(
-        "Would send query: \"What is 2 + 2?\" with preset system prompt and receive responses"
-    );
-    println!();
-
-    Ok(())
+    let options = ClaudeAgentOptions::new().system_prompt(SystemPrompt::Preset {
+        preset: "claude_code".to_string(),
+        append: None,
+    });
+    ask(options).await
 }
 
-async fn preset_with_append() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn preset_with_append() -> Result<(), AgentError> {
     println!("=== Preset System Prompt with Append ===");
 
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
-    let client = Client::new(Arc::new(Mutex::new(transport)));
-
-    let user_message = UserMessage {
-        content: "What is 2 + 2?".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
-
-    // In a real implementation, we would send the query with a preset system prompt and append and receive responses
-    println!("Would send query: \"What is 2 + 2?\" with preset system prompt and append and receive responses");
-    println!();
-
-    Ok(())
+    let options = ClaudeAgentOptions::new().system_prompt(SystemPrompt::Preset {
+        preset: "claude_code".to_string(),
+        append: Some("Always end your response with a fun fact.".to_string()),
+    });
+    ask(options).await
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> Result<(), AgentError> {
     no_system_prompt().await?;
     string_system_prompt().await?;
     preset_system_prompt().await?;
@@ -1,20 +1,15 @@
 // Example demonstrating tool permission callbacks to control tool usage
 
+use futures::StreamExt;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio;
-use tokio::sync::Mutex;
 
 use claude_agent_sdk::error::AgentError;
 use claude_agent_sdk::internal::query::{
-    PermissionResult, PermissionResultAllow, PermissionResultDeny, Query, ToolPermissionContext,
+    PermissionResult, PermissionResultAllow, PermissionResultDeny, ToolPermissionContext,
 };
-use claude_agent_sdk::transport::Transport;
-use claude_agent_sdk::types::{
-    ContentBlock, Message, TextBlock, ToolResultBlock, ToolUseBlock, UserMessage,
-};
-use claude_agent_sdk::{Client, SubprocessCLITransport};
+use claude_agent_sdk::types::{ClaudeAgentOptions, ContentBlock, Message};
+use claude_agent_sdk::ClaudeSDKClient;
 
 async fn allow_all_tools(
     _tool_name: String,
@@ -27,7 +22,6 @@ async fn allow_all_tools(
     // Always allow all tools in this example
     println!("   ✅ Automatically allowing {}", _tool_name);
     Ok(PermissionResult::Allow(PermissionResultAllow {
-        behavior: "allow".to_string(),
         updated_input: None,
         updated_permissions: None,
     }))
@@ -47,7 +41,6 @@ async fn deny_dangerous_tools(
             if file_path.starts_with("/etc/") || file_path.starts_with("/usr/") {
                 println!("   ❌ Denying write to system directory: {}", file_path);
                 return Ok(PermissionResult::Deny(PermissionResultDeny {
-                    behavior: "deny".to_string(),
                     message: format!("Cannot write to system directory: {}", file_path),
                     interrupt: false,
                 }));
@@ -66,7 +59,6 @@ async fn deny_dangerous_tools(
                 let mut modified_input = input_data.clone();
                 modified_input.insert("file_path".to_string(), Value::String(safe_path));
                 return Ok(PermissionResult::Allow(PermissionResultAllow {
-                    behavior: "allow".to_string(),
                     updated_input: Some(modified_input),
                     updated_permissions: None,
                 }));
@@ -83,7 +75,6 @@ async fn deny_dangerous_tools(
                 if command.contains(dangerous) {
                     println!("   ❌ Denying dangerous command: {}", command);
                     return Ok(PermissionResult::Deny(PermissionResultDeny {
-                        behavior: "deny".to_string(),
                         message: format!("Dangerous command pattern detected: {}", dangerous),
                         interrupt: false,
                     }));
@@ -93,7 +84,6 @@ async fn deny_dangerous_tools(
             // Allow but log the command
             println!("   ✅ Allowing bash command: {}", command);
             return Ok(PermissionResult::Allow(PermissionResultAllow {
-                behavior: "allow".to_string(),
                 updated_input: None,
                 updated_permissions: None,
             }));
@@ -103,34 +93,51 @@ async fn deny_dangerous_tools(
     // For all other tools, allow by default
     println!("   ✅ Allowing tool: {}", tool_name);
     Ok(PermissionResult::Allow(PermissionResultAllow {
-        behavior: "allow".to_string(),
         updated_input: None,
         updated_permissions: None,
     }))
 }
 
-async fn query_with_tool_permissions() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    println!("=== Tool Permission Callback Examples ===");
+// Prints what Claude says until the end of the response
+async fn run(prompt: &str, options: ClaudeAgentOptions) -> Result<(), AgentError> {
+    let mut client = ClaudeSDKClient::with_options(options);
+    println!("User: {}", prompt);
+    client.connect(Some(prompt)).await?;
+    let mut response = Box::pin(client.receive_response());
+    while let Some(message) = response.next().await {
+        if let Message::Assistant(assistant) = message? {
+            for block in assistant.content {
+                if let ContentBlock::Text(text) = block {
+                    println!("Claude: {}", text.text);
+                }
+            }
+        }
+    }
+    drop(response);
+    client.disconnect().await?;
+    println!();
+    Ok(())
+}
 
-    let transport = SubprocessCLITransport::new("claude", &["--mode", "streaming"])?;
+async fn query_with_tool_permissions() -> Result<(), AgentError> {
+    println!("=== Tool Permission Callback Examples ===");
 
-    // Create a query with tool permission callback
-    // Note: In a full implementation, we would properly integrate the tool permission callback
-    // For now, we're just demonstrating how it would be structured
-    let user_message = UserMessage {
-        content: "Please do the following:\n1. List the files in the current directory\n2. Create a simple Python hello world script at hello.py\n3. Run the script to test it".to_string(),
-        parent_tool_use_id: None,
-    };
-    let messages = vec![Message::User(user_message)];
+    // The CLI asks the callback before every tool use
+    let options = ClaudeAgentOptions::new().can_use_tool(allow_all_tools);
+    run("Read README.md and summarize it in one sentence.", options).await?;
 
-    println!("Would send query to Claude with tool permission callbacks and process responses");
-    println!();
+    let options = ClaudeAgentOptions::new().can_use_tool(deny_dangerous_tools);
+    run(
+        "Write a hello world Python script to hello.py, then run it with Bash.",
+        options,
+    )
+    .await?;
 
     Ok(())
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> Result<(), AgentError> {
     query_with_tool_permissions().await?;
     Ok(())
 }
@@ -0,0 +1,289 @@
+//! Derive macros for `claude-agent-sdk`.
+//!
+//! Use them through the `derive` feature of `claude-agent-sdk` rather than
+//! depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Expr, Fields, Lit, LitStr, Meta,
+};
+
+/// Derives `claude_agent_sdk::schema::InputSchema` for a struct with named
+/// fields or an enum of unit variants.
+#[proc_macro_derive(InputSchema, attributes(serde))]
+pub fn derive_input_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let description = option_tokens(doc_comment(&input.attrs));
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let Fields::Named(named) = &data.fields else {
+                return Err(syn::Error::new(
+                    data.fields.span(),
+                    "InputSchema can only be derived for structs with named fields",
+                ));
+            };
+            let mut fields = Vec::new();
+            let mut flattened = Vec::new();
+            for field in &named.named {
+                let attrs = SerdeAttrs::parse(&field.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                let ty = &field.ty;
+                let has_default = attrs.default || container.default;
+                if attrs.flatten {
+                    // The inner fields sit next to the outer ones in the JSON
+                    flattened.push(quote! {
+                        let schema = ::claude_agent_sdk::schema::flatten_schema(
+                            schema,
+                            <#ty as ::claude_agent_sdk::schema::InputSchema>::input_schema(),
+                            <#ty as ::claude_agent_sdk::schema::InputSchema>::REQUIRED && !#has_default,
+                        );
+                    });
+                    continue;
+                }
+                let ident = field.ident.as_ref().expect("named field");
+                let name = attrs.rename.unwrap_or_else(|| {
+                    let name = ident.to_string();
+                    let name = name.strip_prefix("r#").unwrap_or(&name).to_string();
+                    match &container.rename_all {
+                        Some(rule) => rename_field(&name, rule),
+                        None => name,
+                    }
+                });
+                let field_description = option_tokens(doc_comment(&field.attrs));
+                fields.push(quote! {
+                    (
+                        #name,
+                        <#ty as ::claude_agent_sdk::schema::InputSchema>::input_schema(),
+                        #field_description,
+                        <#ty as ::claude_agent_sdk::schema::InputSchema>::REQUIRED && !#has_default,
+                    )
+                });
+            }
+            quote! {
+                let schema =
+                    ::claude_agent_sdk::schema::object_schema(#description, vec![#(#fields),*]);
+                #(#flattened)*
+                schema
+            }
+        }
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new(
+                        variant.span(),
+                        "InputSchema can only be derived for enums whose variants have no fields",
+                    ));
+                }
+                let attrs = SerdeAttrs::parse(&variant.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                variants.push(attrs.rename.unwrap_or_else(|| {
+                    let name = variant.ident.to_string();
+                    match &container.rename_all {
+                        Some(rule) => rename_variant(&name, rule),
+                        None => name,
+                    }
+                }));
+            }
+            quote! {
+                ::claude_agent_sdk::schema::string_enum_schema(#description, &[#(#variants),*])
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "InputSchema cannot be derived for unions",
+            ))
+        }
+    };
+
+    let type_params: Vec<_> = input
+        .generics
+        .type_params()
+        .map(|p| p.ident.clone())
+        .collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::claude_agent_sdk::schema::InputSchema));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::claude_agent_sdk::schema::InputSchema for #name #ty_generics #where_clause {
+            fn input_schema() -> ::claude_agent_sdk::schema::Value {
+                #body
+            }
+        }
+    })
+}
+
+fn option_tokens(value: Option<String>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(::core::option::Option::Some(#value)),
+        None => quote!(::core::option::Option::None),
+    }
+}
+
+// Joins `///` lines, dropping the leading space rustdoc keeps
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(text) => Some(text.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let text = lines.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+// The subset of serde attributes that changes the JSON shape of a schema
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if meta.input.peek(syn::Token![=]) {
+                        parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else {
+                        // `rename(deserialize = "...")` is the name the input arrives under
+                        meta.parse_nested_meta(|inner| {
+                            if inner.path.is_ident("deserialize") {
+                                parsed.rename = Some(inner.value()?.parse::<LitStr>()?.value());
+                                Ok(())
+                            } else {
+                                skip_meta(inner)
+                            }
+                        })?;
+                    }
+                } else if meta.path.is_ident("rename_all") {
+                    let rule = meta.value()?.parse::<LitStr>()?;
+                    if !RENAME_RULES.contains(&rule.value().as_str()) {
+                        return Err(syn::Error::new(rule.span(), "unknown rename_all rule"));
+                    }
+                    parsed.rename_all = Some(rule.value());
+                } else if meta.path.is_ident("default") {
+                    parsed.default = true;
+                    return skip_meta(meta);
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    parsed.flatten = true;
+                } else {
+                    return skip_meta(meta);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+// Consumes an attribute this macro does not care about
+fn skip_meta(meta: ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(skip_meta)?;
+    }
+    Ok(())
+}
+
+const RENAME_RULES: &[&str] = &[
+    "lowercase",
+    "UPPERCASE",
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "kebab-case",
+    "SCREAMING-KEBAB-CASE",
+];
+
+// Field names start out in snake_case
+fn rename_field(name: &str, rule: &str) -> String {
+    match rule {
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => name.to_ascii_uppercase(),
+        "PascalCase" | "camelCase" => {
+            let mut renamed = String::new();
+            let mut capitalize = rule == "PascalCase";
+            for ch in name.chars() {
+                if ch == '_' {
+                    capitalize = true;
+                } else if capitalize {
+                    renamed.push(ch.to_ascii_uppercase());
+                    capitalize = false;
+                } else {
+                    renamed.push(ch);
+                }
+            }
+            renamed
+        }
+        "kebab-case" => name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => name.replace('_', "-").to_ascii_uppercase(),
+        _ => name.to_string(),
+    }
+}
+
+// Variant names start out in PascalCase
+fn rename_variant(name: &str, rule: &str) -> String {
+    let mut snake = String::new();
+    for (i, ch) in name.char_indices() {
+        if ch.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(ch.to_ascii_lowercase());
+    }
+    match rule {
+        "lowercase" => name.to_ascii_lowercase(),
+        "UPPERCASE" => name.to_ascii_uppercase(),
+        "camelCase" => {
+            let mut chars = name.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        }
+        "snake_case" => snake,
+        "SCREAMING_SNAKE_CASE" => snake.to_ascii_uppercase(),
+        "kebab-case" => snake.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => snake.replace('_', "-").to_ascii_uppercase(),
+        _ => name.to_string(),
+    }
+}
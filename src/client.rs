@@ -1,8 +1,12 @@
 use crate::error::AgentError;
-use crate::transport::Transport;
+use crate::internal::query::Query;
+use crate::message_parser::parse_message;
+use crate::transport::{SubprocessCLITransport, Transport};
 use crate::types::{
-    AssistantMessage, Message, ResultMessage, StreamEvent, SystemMessage, UserMessage,
+    AssistantMessage, ClaudeAgentOptions, Message, PermissionMode, ResultMessage, StreamEvent,
+    SystemMessage, UserMessage,
 };
+use futures::stream::{self, Stream};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -43,18 +47,14 @@ impl Client {
             if let Some(callback) = on_message {
                 loop {
                     let json_value = self.receive_message().await?;
-                    // Try to parse as Message, if it fails just continue
-                    if let Ok(message) = serde_json::from_value::<Message>(json_value.clone()) {
-                        callback(message.clone());
-
-                        // Check if this is a result message which indicates the end of the stream
-                        match message {
-                            Message::Result(_) => break,
-                            _ => continue,
-                        }
-                    } else {
-                        // If it's not a Message variant, it might be a response that we should return
-                        return Ok(json_value);
+                    // Unknown message types still parse, so an error here is a malformed frame
+                    let message = parse_message(&json_value)?;
+                    let is_result = matches!(message, Message::Result(_));
+                    callback(message);
+
+                    // A result message ends the stream
+                    if is_result {
+                        break;
                     }
                 }
             }
@@ -80,19 +80,29 @@ impl Client {
 
     // Additional methods based on the Python SDK implementation
     pub async fn get_next_message(&self) -> Result<Message, AgentError> {
-        let json_value = self.receive_message().await?;
-        // Parse the JSON into the appropriate Message variant
-        let message: Message = serde_json::from_value(json_value)?;
-        Ok(message)
+        read_message(&self.transport).await
     }
 
-    pub async fn send_user_message(&self, content: &str) -> Result<(), AgentError> {
-        let message = UserMessage {
-            content: content.to_string(),
-            parent_tool_use_id: None,
-        };
-        let json_value = serde_json::to_value(message)?;
-        self.send_message(json_value).await
+    /// Every message from the transport, as it arrives.
+    ///
+    /// The stream ends after yielding an error. Dropping it cancels the read in
+    /// progress and releases the transport.
+    pub fn receive_messages(&self) -> impl Stream<Item = Result<Message, AgentError>> + 'static {
+        message_stream(self.transport.clone(), false)
+    }
+
+    /// Messages up to and including the [`ResultMessage`] that ends the current response.
+    pub fn receive_response(&self) -> impl Stream<Item = Result<Message, AgentError>> + 'static {
+        message_stream(self.transport.clone(), true)
+    }
+
+    /// Sends text, content blocks or a whole [`UserMessage`] as user input.
+    pub async fn send_user_message(
+        &self,
+        content: impl Into<UserMessage>,
+    ) -> Result<(), AgentError> {
+        let frame = content.into().to_input_frame("default");
+        self.send_message(frame).await
     }
 
     pub async fn send_assistant_message(
@@ -104,6 +114,8 @@ impl Client {
             content,
             model: model.to_string(),
             parent_tool_use_id: None,
+            id: None,
+            usage: None,
         };
         let json_value = serde_json::to_value(message)?;
         self.send_message(json_value).await
@@ -132,3 +144,225 @@ impl Client {
         self.send_message(json_value).await
     }
 }
+
+/// A long-lived, two-way conversation with the CLI.
+///
+/// [`connect`](Self::connect) spawns the CLI and runs the initialize handshake;
+/// after that prompts can be sent at any time while responses are read as a
+/// stream, and the running turn can be interrupted or reconfigured.
+/// [`disconnect`](Self::disconnect) shuts the CLI down gracefully; dropping the
+/// client kills it.
+///
+/// ```no_run
+/// use claude_agent_sdk::{ClaudeSDKClient, Message};
+/// use futures::StreamExt;
+///
+/// # async fn run() -> Result<(), claude_agent_sdk::AgentError> {
+/// let mut client = ClaudeSDKClient::new();
+/// client.connect(Some("Help me analyze this codebase")).await?;
+/// for prompt in [None, Some("Can you explain the architecture?")] {
+///     if let Some(prompt) = prompt {
+///         client.query(prompt).await?;
+///     }
+///     let mut response = Box::pin(client.receive_response());
+///     while let Some(message) = response.next().await {
+///         if let Message::Result(result) = message? {
+///             println!("{}", result.result.unwrap_or_default());
+///         }
+///     }
+/// }
+/// client.disconnect().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ClaudeSDKClient {
+    options: ClaudeAgentOptions,
+    transport: Option<Arc<dyn Transport + Send + Sync>>,
+    query: Option<Query>,
+}
+
+impl ClaudeSDKClient {
+    /// A client that spawns the CLI with default options.
+    pub fn new() -> Self {
+        Self::with_options(ClaudeAgentOptions::new())
+    }
+
+    pub fn with_options(options: ClaudeAgentOptions) -> Self {
+        ClaudeSDKClient {
+            options,
+            transport: None,
+            query: None,
+        }
+    }
+
+    /// A client that talks over `transport` instead of spawning the CLI.
+    ///
+    /// The transport is used for the first connection only.
+    pub fn new_with_transport(transport: impl Transport + Send + Sync + 'static) -> Self {
+        Self::with_options_and_transport(ClaudeAgentOptions::new(), transport)
+    }
+
+    /// A client configured by `options` that talks over `transport`.
+    ///
+    /// The options still provide hooks, `can_use_tool` and in-process MCP
+    /// servers; the transport is used for the first connection only.
+    pub fn with_options_and_transport(
+        options: ClaudeAgentOptions,
+        transport: impl Transport + Send + Sync + 'static,
+    ) -> Self {
+        let mut client = Self::with_options(options);
+        client.transport = Some(Arc::new(transport));
+        client
+    }
+
+    /// Starts the session, sending `prompt` first when given.
+    ///
+    /// Connecting again first disconnects the current session.
+    pub async fn connect(&mut self, prompt: Option<&str>) -> Result<(), AgentError> {
+        self.start().await?;
+        if let Some(prompt) = prompt {
+            self.query(prompt).await?;
+        }
+        Ok(())
+    }
+
+    /// Starts the session and feeds it `messages` in the background.
+    ///
+    /// The CLI's input stays open while the stream is alive and is closed once
+    /// it ends, after which the CLI exits when it has answered everything.
+    /// [`receive_messages`](Self::receive_messages) then ends after the last
+    /// result instead of reporting the exit.
+    pub async fn connect_with_stream<S>(&mut self, messages: S) -> Result<(), AgentError>
+    where
+        S: Stream<Item = UserMessage> + Send + 'static,
+    {
+        self.start().await?.stream_input(messages).await;
+        Ok(())
+    }
+
+    async fn start(&mut self) -> Result<&Query, AgentError> {
+        self.disconnect().await?;
+        let transport = match self.transport.take() {
+            Some(transport) => transport,
+            None => Arc::new(SubprocessCLITransport::from_options_async(&self.options).await?),
+        };
+        let mut query = Query::from_options(transport, &self.options);
+        query.initialize().await?;
+        Ok(self.query.insert(query))
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.query.is_some()
+    }
+
+    /// Sends a prompt: text, content blocks or a whole [`UserMessage`].
+    pub async fn query(&self, prompt: impl Into<UserMessage>) -> Result<(), AgentError> {
+        self.send_user_message(prompt).await
+    }
+
+    /// Sends a user message. The same as [`query`](Self::query).
+    pub async fn send_user_message(
+        &self,
+        message: impl Into<UserMessage>,
+    ) -> Result<(), AgentError> {
+        self.connected()?
+            .send_message(message.into().to_input_frame("default"))
+            .await
+    }
+
+    /// Every message the CLI sends, as it arrives. Ends after yielding an error.
+    pub fn receive_messages(&self) -> impl Stream<Item = Result<Message, AgentError>> + '_ {
+        self.query_stream(false)
+    }
+
+    /// Messages up to and including the [`ResultMessage`] that ends the current response.
+    pub fn receive_response(&self) -> impl Stream<Item = Result<Message, AgentError>> + '_ {
+        self.query_stream(true)
+    }
+
+    /// Stops the turn that is currently running.
+    pub async fn interrupt(&self) -> Result<(), AgentError> {
+        self.connected()?.interrupt().await
+    }
+
+    pub async fn set_permission_mode(&self, mode: PermissionMode) -> Result<(), AgentError> {
+        self.connected()?.set_permission_mode(mode).await
+    }
+
+    /// Switches the model for the following turns; `None` returns to the default.
+    pub async fn set_model(&self, model: Option<&str>) -> Result<(), AgentError> {
+        self.connected()?.set_model(model).await
+    }
+
+    /// Closes the CLI's input, waits for it to exit and ends the session.
+    /// Does nothing when not connected.
+    pub async fn disconnect(&mut self) -> Result<(), AgentError> {
+        match self.query.take() {
+            Some(mut query) => query.close().await,
+            None => Ok(()),
+        }
+    }
+
+    fn connected(&self) -> Result<&Query, AgentError> {
+        self.query.as_ref().ok_or(AgentError::NotConnected)
+    }
+
+    fn query_stream(
+        &self,
+        until_result: bool,
+    ) -> impl Stream<Item = Result<Message, AgentError>> + '_ {
+        // The state records whether the last message was a result
+        stream::unfold(Some(false), move |state| async move {
+            let after_result = state?;
+            let message = match self.connected() {
+                Ok(query) => match query.receive_message().await {
+                    Ok(frame) => parse_message(&frame).map_err(AgentError::from),
+                    // Streamed input has ended and the CLI exited after answering it
+                    Err(_) if after_result && query.input_closed() => return None,
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            let done = match &message {
+                Ok(Message::Result(_)) => until_result,
+                Ok(_) => false,
+                Err(_) => true,
+            };
+            let is_result = matches!(message, Ok(Message::Result(_)));
+            Some((message, (!done).then_some(is_result)))
+        })
+    }
+}
+
+impl Default for ClaudeSDKClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn read_message(transport: &Mutex<dyn Transport>) -> Result<Message, AgentError> {
+    let json_value = transport
+        .lock()
+        .await
+        .receive()
+        .await
+        .map_err(AgentError::TransportError)?;
+    Ok(parse_message(&json_value)?)
+}
+
+// Reads until an error, or until a result message when `until_result` is set
+fn message_stream(
+    transport: Arc<Mutex<dyn Transport>>,
+    until_result: bool,
+) -> impl Stream<Item = Result<Message, AgentError>> + 'static {
+    stream::unfold(Some(transport), move |transport| async move {
+        let transport = transport?;
+        let message = read_message(&transport).await;
+        let done = match &message {
+            Ok(Message::Result(_)) => until_result,
+            Ok(_) => false,
+            Err(_) => true,
+        };
+        Some((message, (!done).then_some(transport)))
+    })
+}
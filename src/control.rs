@@ -0,0 +1,162 @@
+//! Control protocol messages exchanged with the Claude CLI.
+//!
+//! Control requests travel in both directions over the same stream as regular
+//! messages: the SDK asks the CLI to interrupt or switch models, and the CLI asks
+//! the SDK for tool permissions, hook results and in-process MCP calls. Every
+//! request carries a `request_id` that its response echoes back.
+
+use crate::types::PermissionMode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Hook matcher registration sent with the `initialize` request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HookMatcherConfig {
+    pub matcher: Option<String>,
+    #[serde(rename = "hookCallbackIds")]
+    pub hook_callback_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+}
+
+/// The body of a control request, tagged by `subtype`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "subtype", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Stop the turn that is currently running.
+    Interrupt,
+    /// Start the control session and register hook callbacks.
+    Initialize {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hooks: Option<HashMap<String, Vec<HookMatcherConfig>>>,
+    },
+    /// Switch the permission mode mid-session.
+    SetPermissionMode { mode: PermissionMode },
+    /// Switch the model mid-session; `None` returns to the default model.
+    SetModel { model: Option<String> },
+    /// Sent by the CLI to ask whether a tool may run.
+    CanUseTool {
+        tool_name: String,
+        input: Value,
+        #[serde(default)]
+        permission_suggestions: Option<Vec<Value>>,
+        #[serde(default)]
+        blocked_path: Option<String>,
+    },
+    /// Sent by the CLI to run a hook callback registered during `initialize`.
+    HookCallback {
+        callback_id: String,
+        input: Value,
+        #[serde(default)]
+        tool_use_id: Option<String>,
+    },
+    /// Sent by the CLI to forward a JSON-RPC message to an in-process MCP server.
+    McpMessage { server_name: String, message: Value },
+}
+
+impl ControlRequest {
+    /// The wire name of this request's subtype.
+    pub fn subtype(&self) -> &'static str {
+        match self {
+            ControlRequest::Interrupt => "interrupt",
+            ControlRequest::Initialize { .. } => "initialize",
+            ControlRequest::SetPermissionMode { .. } => "set_permission_mode",
+            ControlRequest::SetModel { .. } => "set_model",
+            ControlRequest::CanUseTool { .. } => "can_use_tool",
+            ControlRequest::HookCallback { .. } => "hook_callback",
+            ControlRequest::McpMessage { .. } => "mcp_message",
+        }
+    }
+}
+
+/// The body of a control response, tagged by `subtype`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "subtype", rename_all = "snake_case")]
+pub enum ControlResponse {
+    Success {
+        request_id: String,
+        #[serde(default)]
+        response: Option<Value>,
+    },
+    Error {
+        request_id: String,
+        error: String,
+    },
+}
+
+impl ControlResponse {
+    pub fn success(request_id: impl Into<String>, response: Option<Value>) -> Self {
+        ControlResponse::Success {
+            request_id: request_id.into(),
+            response,
+        }
+    }
+
+    pub fn error(request_id: impl Into<String>, error: impl Into<String>) -> Self {
+        ControlResponse::Error {
+            request_id: request_id.into(),
+            error: error.into(),
+        }
+    }
+
+    pub fn request_id(&self) -> &str {
+        match self {
+            ControlResponse::Success { request_id, .. } => request_id,
+            ControlResponse::Error { request_id, .. } => request_id,
+        }
+    }
+
+    /// The response payload, or the error message the other side reported.
+    pub fn into_result(self) -> Result<Value, String> {
+        match self {
+            ControlResponse::Success { response, .. } => Ok(response.unwrap_or(Value::Null)),
+            ControlResponse::Error { error, .. } => Err(error),
+        }
+    }
+}
+
+/// A complete `{"type": "control_request", ...}` frame.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "control_request")]
+pub struct SDKControlRequest {
+    pub request_id: String,
+    pub request: ControlRequest,
+}
+
+/// A complete `{"type": "control_response", ...}` frame.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "control_response")]
+pub struct SDKControlResponse {
+    pub response: ControlResponse,
+}
+
+/// Either kind of control frame, for code that reads both.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    ControlRequest {
+        request_id: String,
+        request: ControlRequest,
+    },
+    ControlResponse {
+        response: ControlResponse,
+    },
+}
+
+impl From<SDKControlRequest> for ControlMessage {
+    fn from(frame: SDKControlRequest) -> Self {
+        ControlMessage::ControlRequest {
+            request_id: frame.request_id,
+            request: frame.request,
+        }
+    }
+}
+
+impl From<SDKControlResponse> for ControlMessage {
+    fn from(frame: SDKControlResponse) -> Self {
+        ControlMessage::ControlResponse {
+            response: frame.response,
+        }
+    }
+}
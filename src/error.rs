@@ -1,3 +1,5 @@
+use crate::message_parser::MessageParseError;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -14,9 +16,41 @@ pub enum AgentError {
     #[error("Process error: {0}")]
     ProcessError(String),
 
+    #[error(
+        "Claude Code CLI not found (searched: {}). Install it with `npm install -g @anthropic-ai/claude-code` or set CLAUDE_CODE_CLI_PATH",
+        display_paths(.searched)
+    )]
+    CLINotFound { searched: Vec<PathBuf> },
+
+    #[error("Unsupported Claude Code CLI version at {}: found {found:?}, minimum supported is {minimum}", .path.display())]
+    UnsupportedCLIVersion {
+        path: PathBuf,
+        found: String,
+        minimum: String,
+    },
+
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+
+    #[error("Invalid MCP server config '{name}': {reason}")]
+    InvalidMcpServerConfig { name: String, reason: String },
+
+    #[error("Invalid attachment {}: {reason}", .path.display())]
+    InvalidAttachment { path: PathBuf, reason: String },
+
+    #[error("MCP server error {code}: {message}")]
+    McpError { code: i64, message: String },
+
+    #[error("Not connected; call connect() first")]
+    NotConnected,
+
+    #[deprecated(note = "message parsing now reports `AgentError::MessageParse`")]
     #[error("Message parsing error: {0}")]
     MessageParsingError(String),
 
+    #[error("Message parse error: {0}")]
+    MessageParse(#[from] MessageParseError),
+
     #[error("Invalid message type: {0}")]
     InvalidMessageType(String),
 
@@ -32,3 +66,11 @@ impl AgentError {
         AgentError::CustomError(message.to_string())
     }
 }
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,297 @@
+//! Typed hook events, inputs and outputs.
+//!
+//! The CLI calls a hook with a JSON object tagged by `hook_event_name` and
+//! expects a camelCase JSON object back. [`HookInput`] and [`HookOutput`] mirror
+//! those shapes so callbacks never have to build them by hand.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// The points in a session where the CLI runs hooks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookEvent {
+    PreToolUse,
+    PostToolUse,
+    UserPromptSubmit,
+    Stop,
+    SubagentStop,
+    PreCompact,
+    Notification,
+    SessionStart,
+    SessionEnd,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreToolUse => "PreToolUse",
+            HookEvent::PostToolUse => "PostToolUse",
+            HookEvent::UserPromptSubmit => "UserPromptSubmit",
+            HookEvent::Stop => "Stop",
+            HookEvent::SubagentStop => "SubagentStop",
+            HookEvent::PreCompact => "PreCompact",
+            HookEvent::Notification => "Notification",
+            HookEvent::SessionStart => "SessionStart",
+            HookEvent::SessionEnd => "SessionEnd",
+        }
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Fields the CLI sends with every hook event.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BaseHookInput {
+    pub session_id: String,
+    pub transcript_path: String,
+    pub cwd: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PreToolUseHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub tool_name: String,
+    pub tool_input: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PostToolUseHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub tool_name: String,
+    pub tool_input: Value,
+    pub tool_response: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserPromptSubmitHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub prompt: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StopHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    #[serde(default)]
+    pub stop_hook_active: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PreCompactHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub trigger: String, // "manual" or "auto"
+    #[serde(default)]
+    pub custom_instructions: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NotificationHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionStartHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub source: String, // "startup", "resume", "clear" or "compact"
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionEndHookInput {
+    #[serde(flatten)]
+    pub base: BaseHookInput,
+    pub reason: String,
+}
+
+/// The input for a hook call, tagged by `hook_event_name`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "hook_event_name")]
+pub enum HookInput {
+    PreToolUse(PreToolUseHookInput),
+    PostToolUse(PostToolUseHookInput),
+    UserPromptSubmit(UserPromptSubmitHookInput),
+    Stop(StopHookInput),
+    SubagentStop(StopHookInput),
+    PreCompact(PreCompactHookInput),
+    Notification(NotificationHookInput),
+    SessionStart(SessionStartHookInput),
+    SessionEnd(SessionEndHookInput),
+}
+
+impl HookInput {
+    pub fn event(&self) -> HookEvent {
+        match self {
+            HookInput::PreToolUse(_) => HookEvent::PreToolUse,
+            HookInput::PostToolUse(_) => HookEvent::PostToolUse,
+            HookInput::UserPromptSubmit(_) => HookEvent::UserPromptSubmit,
+            HookInput::Stop(_) => HookEvent::Stop,
+            HookInput::SubagentStop(_) => HookEvent::SubagentStop,
+            HookInput::PreCompact(_) => HookEvent::PreCompact,
+            HookInput::Notification(_) => HookEvent::Notification,
+            HookInput::SessionStart(_) => HookEvent::SessionStart,
+            HookInput::SessionEnd(_) => HookEvent::SessionEnd,
+        }
+    }
+
+    pub fn base(&self) -> &BaseHookInput {
+        match self {
+            HookInput::PreToolUse(input) => &input.base,
+            HookInput::PostToolUse(input) => &input.base,
+            HookInput::UserPromptSubmit(input) => &input.base,
+            HookInput::Stop(input) | HookInput::SubagentStop(input) => &input.base,
+            HookInput::PreCompact(input) => &input.base,
+            HookInput::Notification(input) => &input.base,
+            HookInput::SessionStart(input) => &input.base,
+            HookInput::SessionEnd(input) => &input.base,
+        }
+    }
+
+    /// The tool being run, for the tool-use events.
+    pub fn tool_name(&self) -> Option<&str> {
+        match self {
+            HookInput::PreToolUse(input) => Some(&input.tool_name),
+            HookInput::PostToolUse(input) => Some(&input.tool_name),
+            _ => None,
+        }
+    }
+
+    /// The tool's input, for the tool-use events.
+    pub fn tool_input(&self) -> Option<&Value> {
+        match self {
+            HookInput::PreToolUse(input) => Some(&input.tool_input),
+            HookInput::PostToolUse(input) => Some(&input.tool_input),
+            _ => None,
+        }
+    }
+}
+
+/// A hook's verdict on the action it was called for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookDecision {
+    Block,
+}
+
+/// A `PreToolUse` hook's answer to whether the tool may run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+    Ask,
+}
+
+/// Event-specific part of a hook's output.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HookSpecificOutput {
+    pub hook_event_name: HookEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_decision: Option<PermissionDecision>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_decision_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional_context: Option<String>,
+}
+
+impl HookSpecificOutput {
+    pub fn new(hook_event_name: HookEvent) -> Self {
+        HookSpecificOutput {
+            hook_event_name,
+            permission_decision: None,
+            permission_decision_reason: None,
+            additional_context: None,
+        }
+    }
+}
+
+/// What a hook callback returns to the CLI. The default output changes nothing.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HookOutput {
+    #[serde(rename = "continue", default, skip_serializing_if = "Option::is_none")]
+    pub should_continue: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppress_output: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<HookDecision>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<HookSpecificOutput>,
+}
+
+impl HookOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the session after this hook, showing `reason` to the user.
+    pub fn stop(mut self, reason: impl Into<String>) -> Self {
+        self.should_continue = Some(false);
+        self.stop_reason = Some(reason.into());
+        self
+    }
+
+    pub fn suppress_output(mut self, suppress: bool) -> Self {
+        self.suppress_output = Some(suppress);
+        self
+    }
+
+    pub fn system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+
+    /// Blocks the action, giving Claude `reason` as feedback.
+    pub fn block(mut self, reason: impl Into<String>) -> Self {
+        self.decision = Some(HookDecision::Block);
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Answers a `PreToolUse` hook with a permission decision.
+    pub fn permission_decision(
+        mut self,
+        decision: PermissionDecision,
+        reason: impl Into<String>,
+    ) -> Self {
+        let output = self.specific_output(HookEvent::PreToolUse);
+        output.permission_decision = Some(decision);
+        output.permission_decision_reason = Some(reason.into());
+        self
+    }
+
+    /// Adds context for Claude, e.g. from a `UserPromptSubmit` or `SessionStart` hook.
+    pub fn additional_context(mut self, event: HookEvent, context: impl Into<String>) -> Self {
+        self.specific_output(event).additional_context = Some(context.into());
+        self
+    }
+
+    fn specific_output(&mut self, event: HookEvent) -> &mut HookSpecificOutput {
+        let output = self
+            .hook_specific_output
+            .get_or_insert_with(|| HookSpecificOutput::new(event));
+        output.hook_event_name = event;
+        output
+    }
+}
@@ -1,8 +1,10 @@
 use crate::error::AgentError;
+use crate::internal::query::Query;
+use crate::message_parser::parse_message;
 use crate::transport::Transport;
-use crate::types::Message;
+use crate::types::{ClaudeAgentOptions, Message, UserMessage};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
 #[derive(Default)]
 pub struct InternalClient;
@@ -12,14 +14,78 @@ impl InternalClient {
         Self
     }
 
-    // Simplified version that doesn't depend on missing Query methods
+    /// Sends one prompt over `transport` and streams the response.
+    ///
+    /// The control protocol is initialized first so hooks, `can_use_tool` and
+    /// in-process MCP servers from `options` can answer the CLI. The stream ends after the result message,
+    /// closing the transport; dropping it early drops the transport instead.
     pub async fn process_query(
         &self,
-        _prompt: String,
-        _transport: Option<Arc<Mutex<dyn Transport>>>,
-    ) -> Result<Vec<Message>, AgentError> {
-        // This is a placeholder implementation
-        // A full implementation would need to replicate the Python SDK's InternalClient.process_query functionality
-        Ok(Vec::new())
+        prompt: UserMessage,
+        options: &ClaudeAgentOptions,
+        transport: Arc<dyn Transport + Send + Sync>,
+    ) -> Result<BoxStream<'static, Result<Message, AgentError>>, AgentError> {
+        let mut query = Query::from_options(transport.clone(), options);
+        query.initialize().await?;
+        transport
+            .send(prompt.to_input_frame("default"))
+            .await
+            .map_err(AgentError::TransportError)?;
+
+        Ok(stream::unfold(Some(query), |query| async move {
+            let mut query = query?;
+            let message = match query.receive_message().await {
+                Ok(frame) => parse_message(&frame).map_err(AgentError::from),
+                Err(e) => Err(e),
+            };
+            if matches!(message, Ok(Message::Result(_)) | Err(_)) {
+                // The response is complete; an error closing the CLI would only hide it
+                let _ = query.close().await;
+                return Some((message, None));
+            }
+            Some((message, Some(query)))
+        })
+        .boxed())
+    }
+
+    /// Feeds `messages` over `transport` and streams every response.
+    ///
+    /// Unlike [`process_query`](Self::process_query) the stream does not stop
+    /// at a result: it runs until the CLI exits after the input has ended,
+    /// which ends it quietly when the last message was a result.
+    pub async fn process_query_with_stream<S>(
+        &self,
+        messages: S,
+        options: &ClaudeAgentOptions,
+        transport: Arc<dyn Transport + Send + Sync>,
+    ) -> Result<BoxStream<'static, Result<Message, AgentError>>, AgentError>
+    where
+        S: Stream<Item = UserMessage> + Send + 'static,
+    {
+        let mut query = Query::from_options(transport, options);
+        query.initialize().await?;
+        query.stream_input(messages).await;
+
+        Ok(stream::unfold(Some((query, false)), |state| async move {
+            let (mut query, after_result) = state?;
+            let message = match query.receive_message().await {
+                Ok(frame) => parse_message(&frame).map_err(AgentError::from),
+                Err(e) => {
+                    let finished = after_result && query.input_closed();
+                    let _ = query.close().await;
+                    if finished {
+                        return None;
+                    }
+                    return Some((Err(e), None));
+                }
+            };
+            if message.is_err() {
+                let _ = query.close().await;
+                return Some((message, None));
+            }
+            let is_result = matches!(message, Ok(Message::Result(_)));
+            Some((message, Some((query, is_result))))
+        })
+        .boxed())
     }
 }
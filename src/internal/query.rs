@@ -1,12 +1,34 @@
+use crate::control::{
+    ControlRequest, ControlResponse, HookMatcherConfig, SDKControlRequest, SDKControlResponse,
+};
 use crate::error::AgentError;
+use crate::hooks::{HookEvent, HookInput, HookOutput};
+use crate::mcp::jsonrpc::{JsonRpcResponse, METHOD_NOT_FOUND};
+use crate::mcp::SdkMcpServer;
+use crate::message_parser::parse_message;
 use crate::transport::Transport;
-use crate::types::Message;
+use crate::types::{ClaudeAgentOptions, Message, PermissionMode, UserMessage};
 use async_trait::async_trait;
 use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// How long a control request waits for the CLI to answer.
+const CONTROL_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+// How long streamed input keeps stdin open for callbacks and in-process MCP servers after it ends
+const STREAM_CLOSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+type PendingControlResponses = Arc<Mutex<HashMap<String, oneshot::Sender<Result<Value, String>>>>>;
+type SdkMcpServers = Arc<Mutex<HashMap<String, Arc<SdkMcpServer>>>>;
 
 #[derive(Debug, Clone)]
 pub struct ToolPermissionContext {
@@ -20,42 +42,89 @@ pub enum PermissionResult {
     Deny(PermissionResultDeny),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PermissionResultAllow {
-    pub behavior: String, // "allow"
     pub updated_input: Option<HashMap<String, Value>>,
     pub updated_permissions: Option<Vec<PermissionUpdate>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct PermissionResultDeny {
-    pub behavior: String, // "deny"
     pub message: String,
     pub interrupt: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionBehavior {
+    Allow,
+    Deny,
+    Ask,
+}
+
+/// Where a permission update is saved.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionUpdateDestination {
+    UserSettings,
+    ProjectSettings,
+    LocalSettings,
+    Session,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct PermissionRuleValue {
     pub tool_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub rule_content: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct PermissionUpdate {
-    pub r#type: String, // "addRules", "replaceRules", "removeRules", "setMode", "addDirectories", "removeDirectories"
-    pub rules: Option<Vec<PermissionRuleValue>>,
-    pub behavior: Option<String>, // PermissionBehavior
-    pub mode: Option<String>,     // PermissionMode
-    pub directories: Option<Vec<String>>,
-    pub destination: Option<String>, // PermissionUpdateDestination
+/// A change to the permission rules, tagged by `type` as the CLI sends and expects it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PermissionUpdate {
+    AddRules {
+        rules: Vec<PermissionRuleValue>,
+        behavior: PermissionBehavior,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PermissionUpdateDestination>,
+    },
+    ReplaceRules {
+        rules: Vec<PermissionRuleValue>,
+        behavior: PermissionBehavior,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PermissionUpdateDestination>,
+    },
+    RemoveRules {
+        rules: Vec<PermissionRuleValue>,
+        behavior: PermissionBehavior,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PermissionUpdateDestination>,
+    },
+    SetMode {
+        mode: PermissionMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PermissionUpdateDestination>,
+    },
+    AddDirectories {
+        directories: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PermissionUpdateDestination>,
+    },
+    RemoveDirectories {
+        directories: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PermissionUpdateDestination>,
+    },
 }
 // Type alias for hook callbacks
 pub type HookCallback = Arc<
     dyn Fn(
-            HashMap<String, Value>,
+            HookInput,
             Option<String>,
             HookContext,
-        ) -> BoxFuture<'static, Result<Value, AgentError>>
+        ) -> BoxFuture<'static, Result<HookOutput, AgentError>>
         + Send
         + Sync,
 >;
@@ -74,26 +143,69 @@ pub struct Query {
     transport: Arc<dyn Transport + Send + Sync>,
     is_streaming_mode: bool,
     can_use_tool: Option<CanUseTool>,
-    hooks: Option<HashMap<String, Vec<HookMatcher>>>,
-    sdk_mcp_servers: Option<HashMap<String, String>>, // Simplified for now
+    hooks: Option<HashMap<HookEvent, Vec<HookMatcher>>>,
+    sdk_mcp_servers: SdkMcpServers,
 
     // Control protocol state
-    pending_control_responses: Arc<Mutex<HashMap<String, Arc<tokio::sync::Notify>>>>,
-    pending_control_results: Arc<Mutex<HashMap<String, Result<Value, String>>>>,
+    pending_control_responses: PendingControlResponses,
     hook_callbacks: Arc<Mutex<HashMap<String, HookCallback>>>,
     next_callback_id: Arc<Mutex<u32>>,
     request_counter: Arc<Mutex<u32>>,
 
     // Message stream
     message_queue: Arc<Mutex<Vec<Message>>>,
+    message_tx: std::sync::Mutex<Option<mpsc::UnboundedSender<Result<Value, AgentError>>>>,
+    message_rx: Mutex<mpsc::UnboundedReceiver<Result<Value, AgentError>>>,
+    reader_task: std::sync::Mutex<Option<JoinHandle<()>>>,
+    results_received: watch::Sender<usize>,
+    input_task: std::sync::Mutex<Option<JoinHandle<()>>>,
+    input_closed: Arc<AtomicBool>,
     initialized: bool,
     closed: bool,
 }
 
-#[derive(Debug, Clone)]
+/// Callbacks to run for hook events whose tool name matches `matcher`.
+#[derive(Clone, Default)]
 pub struct HookMatcher {
-    pub matcher: Option<String>,
-    pub hooks: Vec<String>, // Simplified for now
+    pub matcher: Option<String>, // Tool name pattern, e.g. "Bash" or "Write|Edit"; None matches all
+    pub hooks: Vec<HookCallback>,
+    pub timeout: Option<f64>, // Seconds the CLI waits for these hooks
+}
+
+impl HookMatcher {
+    pub fn new(matcher: Option<&str>) -> Self {
+        HookMatcher {
+            matcher: matcher.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    pub fn hook<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn(HookInput, Option<String>, HookContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<HookOutput, AgentError>> + Send + 'static,
+    {
+        self.hooks
+            .push(Arc::new(move |input, tool_use_id, context| {
+                Box::pin(callback(input, tool_use_id, context))
+            }));
+        self
+    }
+
+    pub fn timeout(mut self, seconds: f64) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+}
+
+impl fmt::Debug for HookMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookMatcher")
+            .field("matcher", &self.matcher)
+            .field("hooks", &format_args!("<{} callback(s)>", self.hooks.len()))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -121,186 +233,491 @@ impl Query {
         transport: Arc<dyn Transport + Send + Sync>,
         is_streaming_mode: bool,
         can_use_tool: Option<CanUseTool>,
-        hooks: Option<HashMap<String, Vec<HookMatcher>>>,
-        sdk_mcp_servers: Option<HashMap<String, String>>,
+        hooks: Option<HashMap<HookEvent, Vec<HookMatcher>>>,
+        sdk_mcp_servers: Option<HashMap<String, Arc<SdkMcpServer>>>,
     ) -> Self {
+        let (message_tx, message_rx) = mpsc::unbounded_channel();
         Query {
             transport,
             is_streaming_mode,
             can_use_tool,
             hooks,
-            sdk_mcp_servers,
+            sdk_mcp_servers: Arc::new(Mutex::new(sdk_mcp_servers.unwrap_or_default())),
             pending_control_responses: Arc::new(Mutex::new(HashMap::new())),
-            pending_control_results: Arc::new(Mutex::new(HashMap::new())),
             hook_callbacks: Arc::new(Mutex::new(HashMap::new())),
             next_callback_id: Arc::new(Mutex::new(0)),
             request_counter: Arc::new(Mutex::new(0)),
             message_queue: Arc::new(Mutex::new(Vec::new())),
+            message_tx: std::sync::Mutex::new(Some(message_tx)),
+            message_rx: Mutex::new(message_rx),
+            reader_task: std::sync::Mutex::new(None),
+            results_received: watch::Sender::new(0),
+            input_task: std::sync::Mutex::new(None),
+            input_closed: Arc::new(AtomicBool::new(false)),
             initialized: false,
             closed: false,
         }
     }
 
+    /// A streaming-mode query with the callbacks and in-process MCP servers from `options`.
+    pub fn from_options(
+        transport: Arc<dyn Transport + Send + Sync>,
+        options: &ClaudeAgentOptions,
+    ) -> Self {
+        Self::new(
+            transport,
+            true,
+            options.can_use_tool.clone(),
+            (!options.hooks.is_empty()).then(|| options.hooks.clone()),
+            Some(options.sdk_mcp_servers()),
+        )
+    }
+
+    /// Starts the background task that reads every frame from the transport.
+    ///
+    /// `control_response` frames are routed to the request waiting for them and
+    /// everything else goes to the message channel read by [`Query::receive_message`].
+    /// Called automatically by anything that needs the reader; calling it again is a no-op.
+    pub fn start(&self) {
+        // The reader owns the only sender, so the channel closes when it stops.
+        let message_tx = self
+            .message_tx
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(message_tx) = message_tx {
+            let inbound = InboundRequests {
+                transport: self.transport.clone(),
+                can_use_tool: self.can_use_tool.clone(),
+                hook_callbacks: self.hook_callbacks.clone(),
+                sdk_mcp_servers: self.sdk_mcp_servers.clone(),
+            };
+            let task = tokio::spawn(read_frames(
+                self.transport.clone(),
+                self.pending_control_responses.clone(),
+                inbound,
+                message_tx,
+                self.results_received.clone(),
+            ));
+            *self
+                .reader_task
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(task);
+        }
+    }
+
+    fn stop(&self) {
+        for task in [&self.reader_task, &self.input_task] {
+            if let Some(task) = task
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .take()
+            {
+                task.abort();
+            }
+        }
+    }
+
+    /// Feeds `messages` to the CLI in the background, then ends its input.
+    ///
+    /// Hooks, `can_use_tool` and in-process MCP servers answer the CLI over
+    /// its stdin, so with any of them input stays open until every message
+    /// sent has its result (or a timeout passes). A send failure stops the
+    /// feed; the reader reports it.
+    pub async fn stream_input<S>(&self, messages: S)
+    where
+        S: Stream<Item = UserMessage> + Send + 'static,
+    {
+        self.start();
+        let transport = self.transport.clone();
+        let input_closed = self.input_closed.clone();
+        let mut results_received = self.results_received.subscribe();
+        // Results for turns sent before this stream do not count towards it
+        let results_before = *results_received.borrow();
+        let wait_for_result = self.can_use_tool.is_some()
+            || self.hooks.as_ref().is_some_and(|hooks| !hooks.is_empty())
+            || !self.sdk_mcp_servers.lock().await.is_empty();
+        let task = tokio::spawn(async move {
+            let mut messages = std::pin::pin!(messages);
+            let mut sent = 0;
+            while let Some(message) = messages.next().await {
+                if transport
+                    .send(message.to_input_frame("default"))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                sent += 1;
+            }
+            if wait_for_result {
+                let _ = tokio::time::timeout(
+                    STREAM_CLOSE_TIMEOUT,
+                    results_received.wait_for(|received| *received >= results_before + sent),
+                )
+                .await;
+            }
+            input_closed.store(true, Ordering::SeqCst);
+            let _ = transport.end_input().await;
+        });
+        if let Some(previous) = self
+            .input_task
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .replace(task)
+        {
+            previous.abort();
+        }
+    }
+
+    /// True once streamed input has ended and the CLI's input was closed.
+    pub fn input_closed(&self) -> bool {
+        self.input_closed.load(Ordering::SeqCst)
+    }
+
     pub async fn initialize(&mut self) -> Result<Option<Value>, AgentError> {
         if !self.is_streaming_mode {
             return Ok(None);
         }
 
         // Build hooks configuration for initialization
-        let mut hooks_config: HashMap<String, Vec<Value>> = HashMap::new();
+        let mut hooks_config: HashMap<String, Vec<HookMatcherConfig>> = HashMap::new();
         if let Some(hooks) = &self.hooks {
             for (event, matchers) in hooks {
                 if !matchers.is_empty() {
-                    hooks_config.insert(event.clone(), Vec::new());
+                    hooks_config.insert(event.to_string(), Vec::new());
                     for matcher in matchers {
-                        let mut callback_ids = Vec::new();
-                        for _callback in &matcher.hooks {
+                        let mut hook_callback_ids = Vec::new();
+                        for callback in &matcher.hooks {
                             let mut next_callback_id = self.next_callback_id.lock().await;
                             let callback_id = format!("hook_{}", next_callback_id);
                             *next_callback_id += 1;
-                            callback_ids.push(Value::String(callback_id));
+                            // Register under the id the CLI will call back with
+                            self.hook_callbacks
+                                .lock()
+                                .await
+                                .insert(callback_id.clone(), callback.clone());
+                            hook_callback_ids.push(callback_id);
                         }
-                        hooks_config.get_mut(event).unwrap().push(json!({
-                            "matcher": matcher.matcher,
-                            "hookCallbackIds": callback_ids,
-                        }));
+                        hooks_config
+                            .get_mut(event.as_str())
+                            .unwrap()
+                            .push(HookMatcherConfig {
+                                matcher: matcher.matcher.clone(),
+                                hook_callback_ids,
+                                timeout: matcher.timeout,
+                            });
                     }
                 }
             }
         }
 
         // Send initialize request
-        let request = json!({
-            "subtype": "initialize",
-            "hooks": if hooks_config.is_empty() { None } else { Some(hooks_config) },
-        });
+        let request = ControlRequest::Initialize {
+            hooks: if hooks_config.is_empty() {
+                None
+            } else {
+                Some(hooks_config)
+            },
+        };
 
-        let response = self.send_control_request(&request).await?;
+        let response = self.send_control_request(request).await?;
         self.initialized = true;
         Ok(Some(response))
     }
 
-    async fn send_control_request(&self, request: &Value) -> Result<Value, AgentError> {
+    async fn send_control_request(&self, request: ControlRequest) -> Result<Value, AgentError> {
         if !self.is_streaming_mode {
             return Err(AgentError::ProcessError(
                 "Control requests require streaming mode".to_string(),
             ));
         }
 
-        // Generate unique request ID
-        let mut request_counter = self.request_counter.lock().await;
-        *request_counter += 1;
-        let request_id = format!("req_{}_{}", request_counter, uuid::Uuid::new_v4());
+        self.start();
 
-        // Create event for response
-        let event = Arc::new(tokio::sync::Notify::new());
+        // Generate unique request ID
+        let request_id = {
+            let mut request_counter = self.request_counter.lock().await;
+            *request_counter += 1;
+            format!("req_{}_{}", request_counter, uuid::Uuid::new_v4())
+        };
+
+        // Register the waiter before sending so a fast response cannot be missed
+        let (response_tx, response_rx) = oneshot::channel();
         self.pending_control_responses
             .lock()
             .await
-            .insert(request_id.clone(), event.clone());
+            .insert(request_id.clone(), response_tx);
 
         // Build and send request
-        let control_request = json!({
-            "type": "control_request",
-            "request_id": request_id,
-            "request": request,
-        });
+        let subtype = request.subtype();
+        let control_request = serde_json::to_value(SDKControlRequest {
+            request_id: request_id.clone(),
+            request,
+        })?;
+
+        if let Err(e) = self.transport.send(control_request).await {
+            self.pending_control_responses
+                .lock()
+                .await
+                .remove(&request_id);
+            return Err(AgentError::TransportError(e));
+        }
 
-        self.transport.send(control_request).await?;
-
-        // Wait for response with timeout
-        let timeout_duration = std::time::Duration::from_secs(60);
-        match tokio::time::timeout(timeout_duration, event.notified()).await {
-            Ok(_) => {
-                // Get the result
-                let mut results = self.pending_control_results.lock().await;
-                if let Some(result) = results.remove(&request_id) {
-                    // Remove the response event
-                    self.pending_control_responses
-                        .lock()
-                        .await
-                        .remove(&request_id);
-
-                    match result {
-                        Ok(value) => Ok(value),
-                        Err(error) => Err(AgentError::ProcessError(error)),
-                    }
-                } else {
-                    Err(AgentError::ProcessError(
-                        "No result found for control request".to_string(),
-                    ))
-                }
-            }
+        // Wait for the reader task to deliver the response
+        match tokio::time::timeout(CONTROL_REQUEST_TIMEOUT, response_rx).await {
+            Ok(Ok(Ok(value))) => Ok(value),
+            Ok(Ok(Err(error))) => Err(AgentError::ProcessError(error)),
+            Ok(Err(_)) => Err(AgentError::ProcessError(
+                "Transport closed before control response was received".to_string(),
+            )),
             Err(_) => {
-                // Timeout occurred
                 self.pending_control_responses
                     .lock()
                     .await
                     .remove(&request_id);
-                self.pending_control_results
-                    .lock()
-                    .await
-                    .remove(&request_id);
                 Err(AgentError::ProcessError(format!(
                     "Control request timeout: {}",
-                    request
-                        .get("subtype")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("unknown")
+                    subtype
                 )))
             }
         }
     }
 
     pub async fn interrupt(&self) -> Result<(), AgentError> {
-        let request = json!({"subtype": "interrupt"});
-        self.send_control_request(&request).await?;
+        self.send_control_request(ControlRequest::Interrupt).await?;
         Ok(())
     }
 
-    pub async fn set_permission_mode(&self, mode: &str) -> Result<(), AgentError> {
-        let request = json!({
-            "subtype": "set_permission_mode",
-            "mode": mode,
-        });
-        self.send_control_request(&request).await?;
+    pub async fn set_permission_mode(&self, mode: PermissionMode) -> Result<(), AgentError> {
+        self.send_control_request(ControlRequest::SetPermissionMode { mode })
+            .await?;
         Ok(())
     }
 
     pub async fn set_model(&self, model: Option<&str>) -> Result<(), AgentError> {
-        let request = json!({
-            "subtype": "set_model",
-            "model": model,
-        });
-        self.send_control_request(&request).await?;
+        self.send_control_request(ControlRequest::SetModel {
+            model: model.map(str::to_string),
+        })
+        .await?;
         Ok(())
     }
 
-    // Method to add an MCP server
-    pub async fn add_mcp_server(&mut self, name: String, uri: String) -> Result<(), AgentError> {
-        if let Some(ref mut servers) = self.sdk_mcp_servers {
-            servers.insert(name, uri);
-        } else {
-            let mut servers = HashMap::new();
-            servers.insert(name, uri);
-            self.sdk_mcp_servers = Some(servers);
-        }
+    // Method to add an in-process MCP server, keyed by its name
+    pub async fn add_mcp_server(&self, server: SdkMcpServer) -> Result<(), AgentError> {
+        self.sdk_mcp_servers
+            .lock()
+            .await
+            .insert(server.name().to_string(), Arc::new(server));
         Ok(())
     }
 
-    // Method to list MCP servers
-    pub fn list_mcp_servers(&self) -> Option<&HashMap<String, String>> {
-        self.sdk_mcp_servers.as_ref()
+    // Method to list the names of in-process MCP servers
+    pub async fn list_mcp_servers(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.sdk_mcp_servers.lock().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Reads frames until the transport fails, routing control responses to their waiters.
+async fn read_frames(
+    transport: Arc<dyn Transport + Send + Sync>,
+    pending_control_responses: PendingControlResponses,
+    inbound: InboundRequests,
+    message_tx: mpsc::UnboundedSender<Result<Value, AgentError>>,
+    results_received: watch::Sender<usize>,
+) {
+    loop {
+        match transport.receive().await {
+            Ok(frame) => match frame.get("type").and_then(|v| v.as_str()) {
+                Some("control_response") => {
+                    route_control_response(&pending_control_responses, frame).await;
+                }
+                Some("control_request") => {
+                    // Callbacks may take a while; keep reading while they run
+                    tokio::spawn(inbound.clone().answer(frame));
+                }
+                other => {
+                    if other == Some("result") {
+                        results_received.send_modify(|received| *received += 1);
+                    }
+                    if message_tx.send(Ok(frame)).is_err() {
+                        // Nobody is listening any more
+                        break;
+                    }
+                }
+            },
+            Err(e) => {
+                // Fail outstanding requests now instead of letting them time out
+                let reason = format!("Transport closed: {}", e);
+                for (_, waiter) in pending_control_responses.lock().await.drain() {
+                    let _ = waiter.send(Err(reason.clone()));
+                }
+                let _ = message_tx.send(Err(AgentError::TransportError(e)));
+                break;
+            }
+        }
+    }
+}
+
+async fn route_control_response(pending_control_responses: &PendingControlResponses, frame: Value) {
+    // A malformed response has no request to fail, so it is dropped
+    let Ok(SDKControlResponse { response }) = serde_json::from_value(frame) else {
+        return;
+    };
+    let waiter = pending_control_responses
+        .lock()
+        .await
+        .remove(response.request_id());
+    if let Some(waiter) = waiter {
+        let _ = waiter.send(response.into_result());
+    }
+}
+
+/// Answers control requests sent by the CLI using the callbacks given to [`Query`].
+#[derive(Clone)]
+struct InboundRequests {
+    transport: Arc<dyn Transport + Send + Sync>,
+    can_use_tool: Option<CanUseTool>,
+    hook_callbacks: Arc<Mutex<HashMap<String, HookCallback>>>,
+    sdk_mcp_servers: SdkMcpServers,
+}
+
+impl InboundRequests {
+    async fn answer(self, frame: Value) {
+        let request_id = frame
+            .get("request_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let response = match serde_json::from_value::<SDKControlRequest>(frame) {
+            Ok(SDKControlRequest { request, .. }) => match self.handle(request).await {
+                Ok(response) => ControlResponse::success(request_id, Some(response)),
+                Err(e) => ControlResponse::error(request_id, e.to_string()),
+            },
+            Err(e) => ControlResponse::error(request_id, format!("Invalid control request: {}", e)),
+        };
+
+        // If the CLI is gone there is nobody left to tell
+        if let Ok(frame) = serde_json::to_value(SDKControlResponse { response }) {
+            let _ = self.transport.send(frame).await;
+        }
+    }
+
+    async fn handle(&self, request: ControlRequest) -> Result<Value, AgentError> {
+        match request {
+            ControlRequest::CanUseTool {
+                tool_name,
+                input,
+                permission_suggestions,
+                ..
+            } => {
+                let can_use_tool = self.can_use_tool.as_ref().ok_or_else(|| {
+                    AgentError::ProcessError("canUseTool callback is not provided".to_string())
+                })?;
+                let suggestions = permission_suggestions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|suggestion| serde_json::from_value(suggestion).ok())
+                    .collect();
+                let context = ToolPermissionContext {
+                    signal: None,
+                    suggestions,
+                };
+                let original_input: HashMap<String, Value> = serde_json::from_value(input)?;
+                let result = can_use_tool(tool_name, original_input.clone(), context).await?;
+                Ok(permission_result_to_value(result, original_input))
+            }
+            ControlRequest::HookCallback {
+                callback_id,
+                input,
+                tool_use_id,
+            } => {
+                let callback = self
+                    .hook_callbacks
+                    .lock()
+                    .await
+                    .get(&callback_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        AgentError::ProcessError(format!(
+                            "No hook callback found for ID: {}",
+                            callback_id
+                        ))
+                    })?;
+                let input: HookInput = serde_json::from_value(input)?;
+                let output = callback(input, tool_use_id, HookContext { signal: None }).await?;
+                Ok(serde_json::to_value(output)?)
+            }
+            ControlRequest::McpMessage {
+                server_name,
+                message,
+            } => {
+                let server = self.sdk_mcp_servers.lock().await.get(&server_name).cloned();
+                let mcp_response = match server {
+                    Some(server) => server.handle_message(message).await,
+                    None => {
+                        let id = message.get("id").cloned().unwrap_or(Value::Null);
+                        serde_json::to_value(JsonRpcResponse::error(
+                            id,
+                            METHOD_NOT_FOUND,
+                            format!("Server '{}' not found", server_name),
+                        ))?
+                    }
+                };
+                Ok(json!({ "mcp_response": mcp_response }))
+            }
+            other => Err(AgentError::ProcessError(format!(
+                "Unsupported control request subtype: {}",
+                other.subtype()
+            ))),
+        }
+    }
+}
+
+fn permission_result_to_value(
+    result: PermissionResult,
+    original_input: HashMap<String, Value>,
+) -> Value {
+    match result {
+        PermissionResult::Allow(allow) => {
+            let mut response = json!({
+                "behavior": "allow",
+                "updatedInput": allow.updated_input.unwrap_or(original_input),
+            });
+            if let Some(updated_permissions) = allow.updated_permissions {
+                response["updatedPermissions"] = json!(updated_permissions);
+            }
+            response
+        }
+        PermissionResult::Deny(deny) => json!({
+            "behavior": "deny",
+            "message": deny.message,
+            "interrupt": deny.interrupt,
+        }),
     }
 }
 
 impl Query {
-    // Add proper transport integration for receiving messages
-    pub async fn receive_message(&self) -> Result<Value, AgentError> {
+    // Write a frame, such as a user message, to the CLI
+    pub async fn send_message(&self, message: Value) -> Result<(), AgentError> {
         self.transport
-            .receive()
+            .send(message)
             .await
-            .map_err(AgentError::TransportError)
+            .map_err(AgentError::TransportError)?;
+        Ok(())
+    }
+
+    // Receive the next non-control frame from the reader task
+    pub async fn receive_message(&self) -> Result<Value, AgentError> {
+        self.start();
+        match self.message_rx.lock().await.recv().await {
+            Some(frame) => frame,
+            None => Err(AgentError::ProcessError(
+                "Message stream has ended".to_string(),
+            )),
+        }
     }
 
     // Implement message processing methods
@@ -308,39 +725,13 @@ impl Query {
         let mut messages = Vec::new();
         loop {
             let json_value = self.receive_message().await?;
-            if let Ok(message) = serde_json::from_value::<Message>(json_value.clone()) {
-                messages.push(message.clone());
+            let message = parse_message(&json_value)?;
+            let is_result = matches!(message, Message::Result(_));
+            messages.push(message);
 
-                // Check if this is a result message which indicates the end of the stream
-                match message {
-                    Message::Result(_) => break,
-                    _ => continue,
-                }
-            } else {
-                // If it's not a Message variant, it might be a control response
-                if let Some(message_type) = json_value.get("type").and_then(|v| v.as_str()) {
-                    if message_type == "control_response" {
-                        // Handle control response
-                        if let Some(request_id) =
-                            json_value.get("request_id").and_then(|v| v.as_str())
-                        {
-                            let mut results = self.pending_control_results.lock().await;
-                            if let Some(response) = json_value.get("response") {
-                                results.insert(request_id.to_string(), Ok(response.clone()));
-                            } else if let Some(error) =
-                                json_value.get("error").and_then(|v| v.as_str())
-                            {
-                                results.insert(request_id.to_string(), Err(error.to_string()));
-                            }
-
-                            // Notify the waiting task
-                            let pending_responses = self.pending_control_responses.lock().await;
-                            if let Some(event) = pending_responses.get(request_id) {
-                                event.notify_one();
-                            }
-                        }
-                    }
-                }
+            // A result message ends the response
+            if is_result {
+                break;
             }
         }
         Ok(messages)
@@ -361,13 +752,13 @@ impl Query {
     pub async fn execute_hook(
         &self,
         callback_id: &str,
-        data: HashMap<String, Value>,
+        input: HookInput,
         tool_use_id: Option<String>,
         context: HookContext,
-    ) -> Result<Value, AgentError> {
+    ) -> Result<HookOutput, AgentError> {
         let callbacks = self.hook_callbacks.lock().await;
         if let Some(callback) = callbacks.get(callback_id) {
-            callback(data, tool_use_id, context).await
+            callback(input, tool_use_id, context).await
         } else {
             Err(AgentError::ProcessError(format!(
                 "Hook callback not found: {}",
@@ -376,50 +767,6 @@ impl Query {
         }
     }
 
-    // Handle permission updates correctly
-    pub async fn handle_permission_update(
-        &self,
-        update: PermissionUpdate,
-    ) -> Result<(), AgentError> {
-        // This is a placeholder implementation
-        // In a full implementation, this would update the permission system
-        match update.r#type.as_str() {
-            "addRules" => {
-                // Add permission rules
-                println!("Adding permission rules: {:?}", update.rules);
-            }
-            "replaceRules" => {
-                // Replace permission rules
-                println!("Replacing permission rules: {:?}", update.rules);
-            }
-            "removeRules" => {
-                // Remove permission rules
-                println!("Removing permission rules: {:?}", update.rules);
-            }
-            "setMode" => {
-                // Set permission mode
-                if let Some(mode) = update.mode {
-                    self.set_permission_mode(&mode).await?;
-                }
-            }
-            "addDirectories" => {
-                // Add directories to permission system
-                println!("Adding directories: {:?}", update.directories);
-            }
-            "removeDirectories" => {
-                // Remove directories from permission system
-                println!("Removing directories: {:?}", update.directories);
-            }
-            _ => {
-                return Err(AgentError::ProcessError(format!(
-                    "Unknown permission update type: {}",
-                    update.r#type
-                )));
-            }
-        }
-        Ok(())
-    }
-
     // Complete query execution methods
     pub async fn execute_query(
         &self,
@@ -484,11 +831,7 @@ impl Query {
             can_use_tool(tool_name, tool_input, context).await
         } else {
             // Default to allow if no permission callback is set
-            Ok(PermissionResult::Allow(PermissionResultAllow {
-                behavior: "allow".to_string(),
-                updated_input: None,
-                updated_permissions: None,
-            }))
+            Ok(PermissionResult::Allow(PermissionResultAllow::default()))
         }
     }
 
@@ -501,8 +844,15 @@ impl Query {
 
     // Method to close the query
     pub async fn close(&mut self) -> Result<(), AgentError> {
+        if self.closed {
+            return Ok(());
+        }
         self.closed = true;
-        Ok(())
+        self.stop();
+        self.transport
+            .close()
+            .await
+            .map_err(AgentError::TransportError)
     }
 
     // Method to check if the query is closed
@@ -510,3 +860,9 @@ impl Query {
         self.closed
     }
 }
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -2,16 +2,27 @@
 //!
 //! This SDK provides a Rust interface for interacting with Claude agents through various transports.
 
+// Lets `#[derive(InputSchema)]` name this crate from inside it
+extern crate self as claude_agent_sdk;
+
 pub mod client;
+pub mod control;
 pub mod error;
+pub mod hooks;
 pub mod internal;
+pub mod mcp;
 pub mod message_parser;
+pub mod partial;
 pub mod query;
+pub mod schema;
+pub mod tool;
 pub mod transport;
 pub mod types;
+pub mod usage;
 
-pub use client::Client;
+pub use client::{ClaudeSDKClient, Client};
 pub use error::AgentError;
+pub use query::{query, query_with_stream};
 pub use transport::SubprocessCLITransport;
 pub use types::*;
 
@@ -0,0 +1,446 @@
+use crate::error::AgentError;
+#[cfg(feature = "mcp-http")]
+use crate::mcp::config::McpRemoteServerConfig;
+use crate::mcp::config::{McpServerConfig, McpStdioServerConfig};
+use crate::mcp::jsonrpc::{JsonRpcRequest, JsonRpcResponse};
+use crate::mcp::server::{SdkMcpServer, ToolResult};
+use crate::mcp::MCP_PROTOCOL_VERSION;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+/// How long a request waits for the server to answer.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A tool advertised by a server's `tools/list`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: Value,
+}
+
+/// A resource advertised by a server's `resources/list`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// The contents of a resource; text resources set `text`, binary ones set base64 `blob`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct McpPromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A prompt template advertised by a server's `prompts/list`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct McpPrompt {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+/// Talks to an MCP server directly, without going through Claude.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), claude_agent_sdk::AgentError> {
+/// use claude_agent_sdk::mcp::{Client, McpStdioServerConfig};
+///
+/// let client = Client::connect_stdio(&McpStdioServerConfig::new("my-mcp-server")).await?;
+/// for tool in client.list_tools().await? {
+///     println!("{}", tool.name);
+/// }
+/// client.close().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Client {
+    connection: Connection,
+    next_id: AtomicI64,
+    server_info: Value,
+}
+
+enum Connection {
+    Stdio(Box<StdioConnection>),
+    #[cfg(feature = "mcp-http")]
+    Http(HttpConnection),
+    Sdk(Arc<SdkMcpServer>),
+}
+
+impl Client {
+    /// Connects to any server the CLI could use, except SSE servers.
+    pub async fn connect(config: &McpServerConfig) -> Result<Self, AgentError> {
+        match config {
+            McpServerConfig::Stdio(config) => Self::connect_stdio(config).await,
+            #[cfg(feature = "mcp-http")]
+            McpServerConfig::Http(config) => Self::connect_http(config).await,
+            #[cfg(not(feature = "mcp-http"))]
+            McpServerConfig::Http(_) => Err(AgentError::ProcessError(
+                "HTTP MCP servers need the `mcp-http` feature".to_string(),
+            )),
+            McpServerConfig::Sse(_) => Err(AgentError::ProcessError(
+                "The MCP client does not support SSE servers".to_string(),
+            )),
+            McpServerConfig::Sdk(server) => Self::connect_sdk(server.clone()).await,
+        }
+    }
+
+    /// Spawns the server and performs the initialize handshake over its stdin/stdout.
+    pub async fn connect_stdio(config: &McpStdioServerConfig) -> Result<Self, AgentError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().ok_or_else(|| {
+            AgentError::ProcessError("Failed to capture MCP server stdout".to_string())
+        })?;
+        let connection = StdioConnection {
+            child: Mutex::new(child),
+            io: Mutex::new(StdioPipes {
+                stdin,
+                stdout: BufReader::new(stdout).lines(),
+            }),
+        };
+        Self::initialize(Connection::Stdio(Box::new(connection))).await
+    }
+
+    /// Connects to a streamable HTTP server and performs the initialize handshake.
+    #[cfg(feature = "mcp-http")]
+    pub async fn connect_http(config: &McpRemoteServerConfig) -> Result<Self, AgentError> {
+        let http = reqwest::Client::builder()
+            .timeout(DEFAULT_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AgentError::TransportError(Box::new(e)))?;
+        let connection = HttpConnection {
+            http,
+            config: config.clone(),
+            session_id: Mutex::new(None),
+        };
+        Self::initialize(Connection::Http(connection)).await
+    }
+
+    /// Talks to an in-process server, which is handy for testing tools.
+    pub async fn connect_sdk(server: Arc<SdkMcpServer>) -> Result<Self, AgentError> {
+        Self::initialize(Connection::Sdk(server)).await
+    }
+
+    async fn initialize(connection: Connection) -> Result<Self, AgentError> {
+        let mut client = Client {
+            connection,
+            next_id: AtomicI64::new(1),
+            server_info: Value::Null,
+        };
+        client.server_info = client
+            .request(
+                "initialize",
+                Some(json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "claude-agent-sdk-rust",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                })),
+            )
+            .await?;
+        client.notify("notifications/initialized", None).await?;
+        Ok(client)
+    }
+
+    /// The server's answer to `initialize`: protocol version, capabilities and server info.
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>, AgentError> {
+        self.list_all("tools/list", "tools").await
+    }
+
+    /// Calls a tool. A tool that fails is reported through `is_error`, not as an `Err`.
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolResult, AgentError> {
+        let result = self
+            .request(
+                "tools/call",
+                Some(json!({"name": name, "arguments": arguments})),
+            )
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    pub async fn list_resources(&self) -> Result<Vec<McpResource>, AgentError> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<McpResourceContents>, AgentError> {
+        let mut result = self
+            .request("resources/read", Some(json!({ "uri": uri })))
+            .await?;
+        Ok(serde_json::from_value(result["contents"].take())?)
+    }
+
+    pub async fn list_prompts(&self) -> Result<Vec<McpPrompt>, AgentError> {
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    /// Sends any JSON-RPC request and returns its result.
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value, AgentError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request = JsonRpcRequest::new(id, method, params);
+        let response = match &self.connection {
+            Connection::Stdio(connection) => connection.request(&request).await?,
+            #[cfg(feature = "mcp-http")]
+            Connection::Http(connection) => connection.request(&request).await?,
+            Connection::Sdk(server) => serde_json::from_value(
+                server.handle_message(serde_json::to_value(&request)?).await,
+            )?,
+        };
+        response
+            .into_result()
+            .map_err(|error| AgentError::McpError {
+                code: error.code,
+                message: error.message,
+            })
+    }
+
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), AgentError> {
+        let notification = JsonRpcRequest::notification(method, params);
+        match &self.connection {
+            Connection::Stdio(connection) => connection.send(&notification).await,
+            #[cfg(feature = "mcp-http")]
+            Connection::Http(connection) => connection.notify(&notification).await,
+            // In-process servers keep no session state
+            Connection::Sdk(_) => Ok(()),
+        }
+    }
+
+    // Follows `nextCursor` until the server has listed everything
+    async fn list_all<T>(&self, method: &str, key: &str) -> Result<Vec<T>, AgentError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.as_ref().map(|cursor| json!({ "cursor": cursor }));
+            let mut result = self.request(method, params).await?;
+            let page: Vec<T> = serde_json::from_value(result[key].take())?;
+            items.extend(page);
+            match result.get("nextCursor").and_then(Value::as_str) {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    /// Shuts the connection down, stopping the server process for stdio servers.
+    pub async fn close(self) -> Result<(), AgentError> {
+        match self.connection {
+            Connection::Stdio(connection) => connection.close().await,
+            #[cfg(feature = "mcp-http")]
+            Connection::Http(connection) => connection.close().await,
+            Connection::Sdk(_) => Ok(()),
+        }
+    }
+}
+
+struct StdioPipes {
+    stdin: Option<ChildStdin>,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+struct StdioConnection {
+    child: Mutex<Child>,
+    io: Mutex<StdioPipes>,
+}
+
+impl StdioConnection {
+    async fn send(&self, message: &JsonRpcRequest) -> Result<(), AgentError> {
+        let mut io = self.io.lock().await;
+        Self::write(&mut io, message).await
+    }
+
+    async fn write(io: &mut StdioPipes, message: &JsonRpcRequest) -> Result<(), AgentError> {
+        let stdin = io
+            .stdin
+            .as_mut()
+            .ok_or_else(|| AgentError::ProcessError("MCP server stdin is closed".to_string()))?;
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        stdin.write_all(line.as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse, AgentError> {
+        // Holding the pipes for the whole exchange keeps responses in order
+        let mut io = self.io.lock().await;
+        Self::write(&mut io, request).await?;
+        let read = async {
+            loop {
+                let Some(line) = io.stdout.next_line().await? else {
+                    return Err(AgentError::ProcessError(
+                        "MCP server closed its output".to_string(),
+                    ));
+                };
+                // Servers may log to stdout or send their own notifications and requests
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if message.get("method").is_some() {
+                    continue;
+                }
+                let response: JsonRpcResponse = serde_json::from_value(message)?;
+                if Some(&response.id) == request.id.as_ref() {
+                    return Ok(response);
+                }
+            }
+        };
+        tokio::time::timeout(DEFAULT_REQUEST_TIMEOUT, read)
+            .await
+            .map_err(|_| {
+                AgentError::ProcessError(format!("MCP request timed out: {}", request.method))
+            })?
+    }
+
+    async fn close(self) -> Result<(), AgentError> {
+        // Closing stdin is the stdio transport's shutdown signal
+        self.io.lock().await.stdin.take();
+        let mut child = self.child.lock().await;
+        if tokio::time::timeout(Duration::from_secs(2), child.wait())
+            .await
+            .is_err()
+        {
+            child.kill().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mcp-http")]
+const SESSION_HEADER: &str = "mcp-session-id";
+
+#[cfg(feature = "mcp-http")]
+struct HttpConnection {
+    http: reqwest::Client,
+    config: McpRemoteServerConfig,
+    session_id: Mutex<Option<String>>,
+}
+
+#[cfg(feature = "mcp-http")]
+impl HttpConnection {
+    async fn post(&self, message: &JsonRpcRequest) -> Result<reqwest::Response, AgentError> {
+        let mut request = self
+            .http
+            .post(&self.config.url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json, text/event-stream")
+            .body(serde_json::to_vec(message)?);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        if let Some(session_id) = self.session_id.lock().await.as_ref() {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+
+        let response = request.send().await.map_err(http_error)?;
+        if !response.status().is_success() {
+            return Err(AgentError::ProcessError(format!(
+                "MCP server returned HTTP {} for {}",
+                response.status(),
+                message.method
+            )));
+        }
+        if let Some(session_id) = response.headers().get(SESSION_HEADER) {
+            if let Ok(session_id) = session_id.to_str() {
+                *self.session_id.lock().await = Some(session_id.to_string());
+            }
+        }
+        Ok(response)
+    }
+
+    async fn request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse, AgentError> {
+        let response = self.post(request).await?;
+        let is_event_stream = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let body = response.text().await.map_err(http_error)?;
+
+        if !is_event_stream {
+            return Ok(serde_json::from_str(&body)?);
+        }
+        // The server may stream notifications before the response we asked for
+        body.lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .filter_map(|data| serde_json::from_str::<JsonRpcResponse>(data.trim()).ok())
+            .find(|response| Some(&response.id) == request.id.as_ref())
+            .ok_or_else(|| {
+                AgentError::ProcessError(format!(
+                    "MCP server sent no response for {}",
+                    request.method
+                ))
+            })
+    }
+
+    async fn notify(&self, notification: &JsonRpcRequest) -> Result<(), AgentError> {
+        self.post(notification).await?;
+        Ok(())
+    }
+
+    async fn close(self) -> Result<(), AgentError> {
+        // Tell the server the session is over; servers without sessions ignore this
+        if let Some(session_id) = self.session_id.lock().await.take() {
+            let _ = self
+                .http
+                .delete(&self.config.url)
+                .header(SESSION_HEADER, session_id)
+                .send()
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mcp-http")]
+fn http_error(error: reqwest::Error) -> AgentError {
+    AgentError::TransportError(Box::new(error))
+}
@@ -0,0 +1,229 @@
+use crate::error::AgentError;
+use crate::mcp::SdkMcpServer;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A server the CLI starts as a child process and talks to over stdin/stdout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct McpStdioServerConfig {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+impl McpStdioServerConfig {
+    pub fn new(command: impl Into<String>) -> Self {
+        McpStdioServerConfig {
+            command: command.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// A server reached over the network, used for both SSE and streamable HTTP.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct McpRemoteServerConfig {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+}
+
+impl McpRemoteServerConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        McpRemoteServerConfig {
+            url: url.into(),
+            headers: HashMap::new(),
+        }
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// How the CLI reaches one MCP server.
+///
+/// Serializes to the entry the CLI expects under `mcpServers` in `--mcp-config`.
+/// SDK servers are sent as `{"type": "sdk", "name": ...}`; their tools stay in
+/// this process and are called through the control protocol.
+#[derive(Debug, Clone)]
+pub enum McpServerConfig {
+    Stdio(McpStdioServerConfig),
+    Sse(McpRemoteServerConfig),
+    Http(McpRemoteServerConfig),
+    Sdk(Arc<SdkMcpServer>),
+}
+
+// The wire shape of the servers that can be written to a file
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ExternalServerConfig {
+    Stdio(McpStdioServerConfig),
+    Sse(McpRemoteServerConfig),
+    Http(McpRemoteServerConfig),
+}
+
+impl McpServerConfig {
+    pub fn stdio(command: impl Into<String>) -> Self {
+        McpServerConfig::Stdio(McpStdioServerConfig::new(command))
+    }
+
+    pub fn sse(url: impl Into<String>) -> Self {
+        McpServerConfig::Sse(McpRemoteServerConfig::new(url))
+    }
+
+    pub fn http(url: impl Into<String>) -> Self {
+        McpServerConfig::Http(McpRemoteServerConfig::new(url))
+    }
+
+    pub fn sdk(server: impl Into<Arc<SdkMcpServer>>) -> Self {
+        McpServerConfig::Sdk(server.into())
+    }
+
+    /// The `type` tag this config is sent with.
+    pub fn server_type(&self) -> &'static str {
+        match self {
+            McpServerConfig::Stdio(_) => "stdio",
+            McpServerConfig::Sse(_) => "sse",
+            McpServerConfig::Http(_) => "http",
+            McpServerConfig::Sdk(_) => "sdk",
+        }
+    }
+
+    /// Checks the config before it reaches the CLI, which would otherwise fail much later.
+    pub fn validate(&self, name: &str) -> Result<(), AgentError> {
+        let invalid = |reason: String| AgentError::InvalidMcpServerConfig {
+            name: name.to_string(),
+            reason,
+        };
+        if name.trim().is_empty() {
+            return Err(invalid("server name must not be empty".to_string()));
+        }
+        match self {
+            McpServerConfig::Stdio(config) => {
+                if config.command.trim().is_empty() {
+                    return Err(invalid("stdio server needs a command".to_string()));
+                }
+            }
+            McpServerConfig::Sse(config) | McpServerConfig::Http(config) => {
+                if !(config.url.starts_with("http://") || config.url.starts_with("https://")) {
+                    return Err(invalid(format!(
+                        "{} server url must start with http:// or https://, got '{}'",
+                        self.server_type(),
+                        config.url
+                    )));
+                }
+                if config.headers.keys().any(|header| header.trim().is_empty()) {
+                    return Err(invalid("header names must not be empty".to_string()));
+                }
+            }
+            McpServerConfig::Sdk(server) => {
+                if server.name().trim().is_empty() {
+                    return Err(invalid("SDK server name must not be empty".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the `mcpServers` of a `.mcp.json` file, validating every entry.
+    pub fn load_file(
+        path: impl AsRef<Path>,
+    ) -> Result<HashMap<String, McpServerConfig>, AgentError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let file: Value = serde_json::from_str(&contents)?;
+        // Accept both the `.mcp.json` layout and a bare map of servers
+        let servers = file.get("mcpServers").cloned().unwrap_or(file);
+        let servers: HashMap<String, McpServerConfig> = serde_json::from_value(servers)?;
+        for (name, config) in &servers {
+            config.validate(name)?;
+        }
+        Ok(servers)
+    }
+}
+
+impl From<McpStdioServerConfig> for McpServerConfig {
+    fn from(config: McpStdioServerConfig) -> Self {
+        McpServerConfig::Stdio(config)
+    }
+}
+
+impl From<SdkMcpServer> for McpServerConfig {
+    fn from(server: SdkMcpServer) -> Self {
+        McpServerConfig::Sdk(Arc::new(server))
+    }
+}
+
+impl From<Arc<SdkMcpServer>> for McpServerConfig {
+    fn from(server: Arc<SdkMcpServer>) -> Self {
+        McpServerConfig::Sdk(server)
+    }
+}
+
+impl Serialize for McpServerConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            McpServerConfig::Stdio(config) => {
+                ExternalServerConfig::Stdio(config.clone()).serialize(serializer)
+            }
+            McpServerConfig::Sse(config) => {
+                ExternalServerConfig::Sse(config.clone()).serialize(serializer)
+            }
+            McpServerConfig::Http(config) => {
+                ExternalServerConfig::Http(config.clone()).serialize(serializer)
+            }
+            McpServerConfig::Sdk(server) => {
+                serde_json::json!({"type": "sdk", "name": server.name()}).serialize(serializer)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for McpServerConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = Value::deserialize(deserializer)?;
+        let Some(object) = value.as_object_mut() else {
+            return Err(D::Error::custom("MCP server config must be an object"));
+        };
+        // `.mcp.json` files leave out the type for stdio servers
+        match object.get("type").and_then(Value::as_str) {
+            None => {
+                object.insert("type".to_string(), Value::String("stdio".to_string()));
+            }
+            Some("sdk") => {
+                return Err(D::Error::custom(
+                    "SDK MCP servers run in-process and cannot be loaded from config",
+                ))
+            }
+            Some(_) => {}
+        }
+        let config = serde_json::from_value(value).map_err(D::Error::custom)?;
+        Ok(match config {
+            ExternalServerConfig::Stdio(config) => McpServerConfig::Stdio(config),
+            ExternalServerConfig::Sse(config) => McpServerConfig::Sse(config),
+            ExternalServerConfig::Http(config) => McpServerConfig::Http(config),
+        })
+    }
+}
@@ -0,0 +1,19 @@
+//! Model Context Protocol support.
+//!
+//! [`SdkMcpServer`] runs MCP tools inside this process: the CLI forwards the
+//! server's JSON-RPC traffic over the control protocol instead of talking to a
+//! separate server binary. [`Client`] goes the other way and talks to an
+//! external MCP server directly.
+
+pub mod client;
+pub mod config;
+pub mod jsonrpc;
+pub mod server;
+
+pub use client::{Client, McpPrompt, McpPromptArgument, McpResource, McpResourceContents, McpTool};
+pub use config::{McpRemoteServerConfig, McpServerConfig, McpStdioServerConfig};
+pub use jsonrpc::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
+pub use server::{SdkMcpServer, SdkMcpTool, ToolContent, ToolHandler, ToolResult};
+
+/// MCP protocol revision spoken by the in-process server and the client.
+pub const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
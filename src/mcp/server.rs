@@ -0,0 +1,341 @@
+use crate::error::AgentError;
+use crate::mcp::jsonrpc::{
+    JsonRpcRequest, JsonRpcResponse, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND,
+};
+use crate::mcp::MCP_PROTOCOL_VERSION;
+use crate::schema::InputSchema;
+use crate::tool::{Tool, ToolContext};
+use crate::types::ToolResultBlock;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Runs a tool with the arguments Claude passed to it.
+pub type ToolHandler =
+    Arc<dyn Fn(Value) -> BoxFuture<'static, Result<ToolResult, AgentError>> + Send + Sync>;
+
+/// One piece of a tool's output.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ToolContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String, // Base64 encoded
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    /// A resource embedded in the result; `resource` holds its uri and text or blob.
+    Resource {
+        resource: Value,
+    },
+}
+
+/// The output of a tool call in MCP's `tools/call` result shape.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ToolResult {
+    pub content: Vec<ToolContent>,
+    #[serde(rename = "isError", default, skip_serializing_if = "is_false")]
+    pub is_error: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl ToolResult {
+    pub fn text(text: impl Into<String>) -> Self {
+        ToolResult {
+            content: vec![ToolContent::Text { text: text.into() }],
+            is_error: false,
+        }
+    }
+
+    /// A result that tells Claude the tool failed, so it can react instead of the turn aborting.
+    pub fn error(message: impl Into<String>) -> Self {
+        ToolResult {
+            content: vec![ToolContent::Text {
+                text: message.into(),
+            }],
+            is_error: true,
+        }
+    }
+
+    pub fn image(data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        ToolResult {
+            content: vec![ToolContent::Image {
+                data: data.into(),
+                mime_type: mime_type.into(),
+            }],
+            is_error: false,
+        }
+    }
+
+    /// A result carrying `value` as pretty-printed JSON text.
+    pub fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_string_pretty(value) {
+            Ok(text) => ToolResult::text(text),
+            Err(e) => ToolResult::error(format!("Failed to serialize tool output: {}", e)),
+        }
+    }
+
+    /// The result as the `tool_result` block that answers `tool_use_id`.
+    pub fn into_block(self, tool_use_id: impl Into<String>) -> ToolResultBlock {
+        let content: Vec<Value> = self
+            .content
+            .into_iter()
+            .map(|content| match content {
+                ToolContent::Text { text } => json!({"type": "text", "text": text}),
+                ToolContent::Image { data, mime_type } => json!({
+                    "type": "image",
+                    "source": {"type": "base64", "media_type": mime_type, "data": data},
+                }),
+                // Messages have no resource block, so embedded resources are passed as text
+                ToolContent::Resource { resource } => {
+                    let text = match resource.get("text").and_then(Value::as_str) {
+                        Some(text) => text.to_string(),
+                        None => resource.to_string(),
+                    };
+                    json!({"type": "text", "text": text})
+                }
+            })
+            .collect();
+        ToolResultBlock {
+            tool_use_id: tool_use_id.into(),
+            content: Some(Value::Array(content)),
+            is_error: Some(self.is_error),
+        }
+    }
+}
+
+impl From<String> for ToolResult {
+    fn from(text: String) -> Self {
+        ToolResult::text(text)
+    }
+}
+
+impl From<&str> for ToolResult {
+    fn from(text: &str) -> Self {
+        ToolResult::text(text)
+    }
+}
+
+impl From<Value> for ToolResult {
+    fn from(value: Value) -> Self {
+        ToolResult::json(&value)
+    }
+}
+
+impl From<ToolContent> for ToolResult {
+    fn from(content: ToolContent) -> Self {
+        ToolResult {
+            content: vec![content],
+            is_error: false,
+        }
+    }
+}
+
+impl From<Vec<ToolContent>> for ToolResult {
+    fn from(content: Vec<ToolContent>) -> Self {
+        ToolResult {
+            content,
+            is_error: false,
+        }
+    }
+}
+
+/// A tool served by an [`SdkMcpServer`].
+#[derive(Clone)]
+pub struct SdkMcpTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    pub handler: ToolHandler,
+}
+
+impl SdkMcpTool {
+    pub fn new<F, Fut>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResult, AgentError>> + Send + 'static,
+    {
+        SdkMcpTool {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            handler: Arc::new(move |arguments| Box::pin(handler(arguments))),
+        }
+    }
+
+    /// A tool whose handler receives its input already deserialized into `T`.
+    ///
+    /// The input schema is `T`'s; arguments that do not fit it are answered with
+    /// an error result so Claude can retry with corrected input.
+    pub fn typed<T, F, Fut>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        T: InputSchema + DeserializeOwned + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResult, AgentError>> + Send + 'static,
+    {
+        let name = name.into();
+        let tool_name = name.clone();
+        let handler = Arc::new(handler);
+        SdkMcpTool {
+            name,
+            description: description.into(),
+            input_schema: T::input_schema(),
+            handler: Arc::new(
+                move |arguments| match serde_json::from_value::<T>(arguments) {
+                    Ok(input) => handler(input).boxed(),
+                    Err(e) => {
+                        let message = format!("Invalid input for tool '{}': {}", tool_name, e);
+                        async move { Ok(ToolResult::error(message)) }.boxed()
+                    }
+                },
+            ),
+        }
+    }
+
+    /// Serves a [`Tool`]; the MCP path has no tool use id, so its context is empty.
+    pub fn from_tool<T: Tool>(tool: T) -> Self {
+        let definition = tool.definition();
+        let tool = Arc::new(tool);
+        SdkMcpTool {
+            name: definition.name,
+            description: definition.description,
+            input_schema: definition.input_schema,
+            handler: Arc::new(move |arguments| {
+                let tool = tool.clone();
+                async move { Ok(crate::tool::run(&*tool, arguments, ToolContext::default()).await) }
+                    .boxed()
+            }),
+        }
+    }
+
+    fn definition(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": self.input_schema,
+        })
+    }
+}
+
+impl fmt::Debug for SdkMcpTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SdkMcpTool")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("input_schema", &self.input_schema)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An MCP server whose tools run in this process.
+///
+/// Register it in the options with [`crate::mcp::McpServerConfig::sdk`] and hand the
+/// server to [`crate::Query`], which answers the CLI's `mcp_message` requests with it.
+#[derive(Debug, Clone)]
+pub struct SdkMcpServer {
+    name: String,
+    version: String,
+    tools: Vec<SdkMcpTool>,
+}
+
+impl SdkMcpServer {
+    pub fn new(name: impl Into<String>) -> Self {
+        SdkMcpServer {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            tools: Vec::new(),
+        }
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Adds a tool, replacing any earlier tool with the same name.
+    pub fn tool(mut self, tool: SdkMcpTool) -> Self {
+        self.tools.retain(|existing| existing.name != tool.name);
+        self.tools.push(tool);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tools(&self) -> &[SdkMcpTool] {
+        &self.tools
+    }
+
+    /// Answers one JSON-RPC message from the CLI.
+    pub async fn handle_message(&self, message: Value) -> Value {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let response = match serde_json::from_value::<JsonRpcRequest>(message) {
+            Ok(request) => self.handle_request(request).await,
+            Err(e) => JsonRpcResponse::error(id, INVALID_REQUEST, e.to_string()),
+        };
+        serde_json::to_value(response).unwrap_or(Value::Null)
+    }
+
+    async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let id = request.id.unwrap_or(Value::Null);
+        match request.method.as_str() {
+            "initialize" => JsonRpcResponse::success(
+                id,
+                json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": self.name, "version": self.version},
+                }),
+            ),
+            "notifications/initialized" => JsonRpcResponse::success(id, json!({})),
+            "tools/list" => {
+                let tools: Vec<Value> = self.tools.iter().map(SdkMcpTool::definition).collect();
+                JsonRpcResponse::success(id, json!({ "tools": tools }))
+            }
+            "tools/call" => {
+                let params = request.params.unwrap_or(Value::Null);
+                let Some(name) = params.get("name").and_then(|v| v.as_str()) else {
+                    return JsonRpcResponse::error(id, INVALID_PARAMS, "Missing tool name");
+                };
+                let Some(tool) = self.tools.iter().find(|tool| tool.name == name) else {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Tool '{}' not found", name),
+                    );
+                };
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                let result = match (tool.handler)(arguments).await {
+                    Ok(result) => result,
+                    Err(e) => ToolResult::error(e.to_string()),
+                };
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap_or(Value::Null))
+            }
+            method => JsonRpcResponse::error(
+                id,
+                METHOD_NOT_FOUND,
+                format!("Method '{}' not found", method),
+            ),
+        }
+    }
+}
@@ -1,78 +1,198 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
+//! Parsing of the messages the CLI writes in `stream-json` mode.
+//!
+//! [`parse_message`] is the one entry point for turning a raw frame into a
+//! [`Message`]. The message types themselves live in [`crate::types`] and are
+//! re-exported here.
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
-pub enum Message {
-    #[serde(rename = "text")]
-    Text(TextMessage),
-    #[serde(rename = "tool_use")]
-    ToolUse(ToolUseMessage),
-    #[serde(rename = "tool_result")]
-    ToolResult(ToolResultMessage),
-    #[serde(rename = "partial_text")]
-    PartialText(PartialTextMessage),
-    #[serde(rename = "partial_tool_use")]
-    PartialToolUse(PartialToolUseMessage),
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+pub use crate::types::{
+    AssistantMessage, ContentBlock, Message, PartialTextMessage, PartialToolUseMessage,
+    ResultMessage, StreamEvent, SystemMessage, TextMessage, ToolResultMessage, ToolUseMessage,
+    UserContent, UserMessage,
+};
+
+/// A frame that could not be parsed into a [`Message`], with the frame itself for debugging.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{reason}")]
+pub struct MessageParseError {
+    pub reason: String,
+    pub raw: Value,
+}
+
+impl MessageParseError {
+    pub fn new(reason: impl Into<String>, raw: &Value) -> Self {
+        MessageParseError {
+            reason: reason.into(),
+            raw: raw.clone(),
+        }
+    }
+}
+
+/// Parses one frame of the CLI's `stream-json` output.
+///
+/// User and assistant messages carry their content in a nested `message`
+/// object, system and result messages are told apart by `subtype`, and
+/// `stream_event` frames wrap a raw API streaming event.
+pub fn parse_message(data: &Value) -> Result<Message, MessageParseError> {
+    let Some(object) = data.as_object() else {
+        return Err(MessageParseError::new(
+            "Message must be a JSON object",
+            data,
+        ));
+    };
+    let Some(message_type) = object.get("type").and_then(Value::as_str) else {
+        return Err(MessageParseError::new(
+            "Message is missing a 'type' field",
+            data,
+        ));
+    };
+
+    match message_type {
+        "user" => parse_user_message(object, data),
+        "assistant" => parse_assistant_message(object, data),
+        "system" => {
+            let subtype = required_str(object, "subtype", data)?;
+            Ok(Message::System(SystemMessage {
+                subtype: subtype.to_string(),
+                data: object.clone().into_iter().collect(),
+            }))
+        }
+        "result" => deserialize("result", data).map(Message::Result),
+        "stream_event" => deserialize("stream_event", data).map(Message::StreamEvent),
+        // Message kinds the SDK itself produces; anything else becomes `Message::Unknown`
+        other => deserialize(other, data),
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct TextMessage {
-    pub message_id: String,
-    pub content: String,
-    pub role: String,
-    #[serde(flatten)]
-    pub metadata: HashMap<String, Value>,
+/// Like [`parse_message`], but rejects message types and content blocks this
+/// SDK does not know instead of returning `Unknown` for them.
+///
+/// Meant for tests that should fail when the CLI's output changes shape.
+pub fn parse_message_strict(data: &Value) -> Result<Message, MessageParseError> {
+    let message = parse_message(data)?;
+    if let Message::Unknown { type_name, .. } = &message {
+        return Err(MessageParseError::new(
+            format!("Unknown message type: {}", type_name),
+            data,
+        ));
+    }
+    let blocks = match &message {
+        Message::Assistant(assistant) => assistant.content.as_slice(),
+        Message::User(UserMessage {
+            content: UserContent::Blocks(blocks),
+            ..
+        }) => blocks.as_slice(),
+        _ => &[],
+    };
+    if let Some(raw) = blocks.iter().find_map(|block| match block {
+        ContentBlock::Unknown { raw } => Some(raw),
+        _ => None,
+    }) {
+        let block_type = raw.get("type").and_then(Value::as_str).unwrap_or("<none>");
+        return Err(MessageParseError::new(
+            format!("Unknown content block type: {}", block_type),
+            data,
+        ));
+    }
+    Ok(message)
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ToolUseMessage {
-    pub message_id: String,
-    pub tool_name: String,
-    pub tool_input: HashMap<String, Value>,
-    pub role: String,
-    #[serde(flatten)]
-    pub metadata: HashMap<String, Value>,
+fn parse_user_message(
+    object: &Map<String, Value>,
+    data: &Value,
+) -> Result<Message, MessageParseError> {
+    let message = nested_message(object, data)?;
+    let content = match message.get("content") {
+        Some(Value::String(text)) => UserContent::Text(text.clone()),
+        Some(Value::Array(_)) => UserContent::Blocks(parse_content_blocks(message, data)?),
+        _ => {
+            return Err(MessageParseError::new(
+                "User message content must be a string or a list of blocks",
+                data,
+            ))
+        }
+    };
+    Ok(Message::User(UserMessage {
+        content,
+        parent_tool_use_id: parent_tool_use_id(object),
+    }))
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ToolResultMessage {
-    pub message_id: String,
-    pub tool_name: String,
-    pub tool_result: Value,
-    pub role: String,
-    #[serde(flatten)]
-    pub metadata: HashMap<String, Value>,
+fn parse_assistant_message(
+    object: &Map<String, Value>,
+    data: &Value,
+) -> Result<Message, MessageParseError> {
+    let message = nested_message(object, data)?;
+    Ok(Message::Assistant(AssistantMessage {
+        content: parse_content_blocks(message, data)?,
+        model: required_str(message, "model", data)?.to_string(),
+        parent_tool_use_id: parent_tool_use_id(object),
+        id: message
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        usage: match message.get("usage") {
+            Some(usage) if !usage.is_null() => {
+                Some(serde_json::from_value(usage.clone()).map_err(|e| {
+                    MessageParseError::new(format!("Invalid assistant message usage: {}", e), data)
+                })?)
+            }
+            _ => None,
+        },
+    }))
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct PartialTextMessage {
-    pub message_id: String,
-    pub content: String,
-    pub role: String,
-    #[serde(flatten)]
-    pub metadata: HashMap<String, Value>,
+fn nested_message<'a>(
+    object: &'a Map<String, Value>,
+    data: &Value,
+) -> Result<&'a Map<String, Value>, MessageParseError> {
+    object
+        .get("message")
+        .and_then(Value::as_object)
+        .ok_or_else(|| MessageParseError::new("Message is missing a 'message' object", data))
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct PartialToolUseMessage {
-    pub message_id: String,
-    pub tool_name: String,
-    pub tool_input: HashMap<String, Value>,
-    pub role: String,
-    #[serde(flatten)]
-    pub metadata: HashMap<String, Value>,
+fn parse_content_blocks(
+    message: &Map<String, Value>,
+    data: &Value,
+) -> Result<Vec<ContentBlock>, MessageParseError> {
+    let Some(blocks) = message.get("content").and_then(Value::as_array) else {
+        return Err(MessageParseError::new(
+            "Message content must be a list of blocks",
+            data,
+        ));
+    };
+    blocks
+        .iter()
+        .map(|block| {
+            serde_json::from_value(block.clone())
+                .map_err(|e| MessageParseError::new(format!("Invalid content block: {}", e), data))
+        })
+        .collect()
 }
 
-impl Message {
-    pub fn message_id(&self) -> &str {
-        match self {
-            Message::Text(msg) => &msg.message_id,
-            Message::ToolUse(msg) => &msg.message_id,
-            Message::ToolResult(msg) => &msg.message_id,
-            Message::PartialText(msg) => &msg.message_id,
-            Message::PartialToolUse(msg) => &msg.message_id,
-        }
-    }
+fn required_str<'a>(
+    object: &'a Map<String, Value>,
+    key: &str,
+    data: &Value,
+) -> Result<&'a str, MessageParseError> {
+    object
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| MessageParseError::new(format!("Message is missing '{}'", key), data))
+}
+
+fn parent_tool_use_id(object: &Map<String, Value>) -> Option<String> {
+    object
+        .get("parent_tool_use_id")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn deserialize<T: DeserializeOwned>(kind: &str, data: &Value) -> Result<T, MessageParseError> {
+    serde_json::from_value(data.clone())
+        .map_err(|e| MessageParseError::new(format!("Invalid {} message: {}", kind, e), data))
 }
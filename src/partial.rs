@@ -0,0 +1,333 @@
+//! Reassembles assistant messages from partial-message stream events.
+//!
+//! With [`include_partial_messages`](crate::types::ClaudeAgentOptions::include_partial_messages)
+//! the CLI forwards the raw Messages API streaming events as
+//! [`StreamEvent`]s. [`MessageAccumulator`] folds them back into an
+//! [`AssistantMessage`] that can be inspected while it is still being written:
+//!
+//! ```
+//! use claude_agent_sdk::partial::{MessageAccumulator, MessageUpdate};
+//! use serde_json::json;
+//!
+//! let mut accumulator = MessageAccumulator::new();
+//! for event in [
+//!     json!({"type": "message_start", "message": {"id": "msg_1", "model": "m", "content": []}}),
+//!     json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+//!     json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+//!     json!({"type": "content_block_stop", "index": 0}),
+//! ] {
+//!     if let Some(MessageUpdate::BlockCompleted { block, .. }) = accumulator.push_event(&event).unwrap() {
+//!         println!("{:?}", block);
+//!     }
+//! }
+//! assert_eq!(accumulator.message().unwrap().content.len(), 1);
+//! ```
+
+use crate::error::AgentError;
+use crate::message_parser::MessageParseError;
+use crate::types::{
+    AssistantMessage, ContentBlock, Message, StreamEvent, TextBlock, ThinkingBlock, ToolUseBlock,
+};
+use crate::usage::Usage;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// What changed after feeding one event to a [`MessageAccumulator`].
+#[derive(Debug, Clone)]
+pub enum MessageUpdate {
+    /// A new message began; anything accumulated before was discarded.
+    MessageStarted,
+    BlockStarted {
+        index: usize,
+    },
+    /// A block at `index` grew by `delta`.
+    BlockDelta {
+        index: usize,
+        delta: Delta,
+    },
+    /// The block at `index` is final, with tool input parsed.
+    BlockCompleted {
+        index: usize,
+        block: ContentBlock,
+    },
+    /// The stop reason or usage changed.
+    MessageDelta {
+        stop_reason: Option<String>,
+    },
+    MessageCompleted {
+        stop_reason: Option<String>,
+        usage: Option<Usage>,
+    },
+}
+
+/// One increment of a content block.
+#[derive(Debug, Clone)]
+pub enum Delta {
+    Text(String),
+    Thinking(String),
+    /// A fragment of a tool call's input; see [`MessageAccumulator::partial_json`].
+    InputJson(String),
+    Signature(String),
+    /// A delta type this SDK does not know yet, with its raw JSON.
+    Unknown {
+        raw: Value,
+    },
+}
+
+/// Builds an [`AssistantMessage`] from `message_start`, `content_block_start`,
+/// `content_block_delta`, `content_block_stop`, `message_delta` and
+/// `message_stop` events.
+///
+/// Events of other types, such as `ping`, are ignored. Use one accumulator per
+/// `parent_tool_use_id` when subagents stream at the same time.
+#[derive(Debug, Clone, Default)]
+pub struct MessageAccumulator {
+    message: Option<AssistantMessage>,
+    partial_json: HashMap<usize, String>,
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+    complete: bool,
+}
+
+impl MessageAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a message from the CLI; anything but a [`Message::StreamEvent`] is ignored.
+    pub fn push_message(&mut self, message: &Message) -> Result<Option<MessageUpdate>, AgentError> {
+        match message {
+            Message::StreamEvent(event) => self.push(event),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn push(&mut self, event: &StreamEvent) -> Result<Option<MessageUpdate>, AgentError> {
+        let update = self.push_event(&event.event)?;
+        if matches!(update, Some(MessageUpdate::MessageStarted)) {
+            if let Some(message) = &mut self.message {
+                message.parent_tool_use_id = event.parent_tool_use_id.clone();
+            }
+        }
+        Ok(update)
+    }
+
+    /// Feeds one raw Messages API streaming event.
+    ///
+    /// Events that do not fit the message so far fail with
+    /// [`AgentError::MessageParse`], carrying the event.
+    pub fn push_event(&mut self, event: &Value) -> Result<Option<MessageUpdate>, AgentError> {
+        self.apply(event)
+            .map_err(|reason| MessageParseError::new(reason, event).into())
+    }
+
+    fn apply(&mut self, event: &Value) -> Result<Option<MessageUpdate>, String> {
+        match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => self.start_message(event),
+            Some("content_block_start") => self.start_block(event),
+            Some("content_block_delta") => self.apply_delta(event),
+            Some("content_block_stop") => self.stop_block(event),
+            Some("message_delta") => self.apply_message_delta(event),
+            Some("message_stop") => {
+                self.in_progress()?;
+                self.complete = true;
+                Ok(Some(MessageUpdate::MessageCompleted {
+                    stop_reason: self.stop_reason.clone(),
+                    usage: self.usage().copied(),
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The message so far, or `None` before the first `message_start`.
+    pub fn message(&self) -> Option<&AssistantMessage> {
+        self.message.as_ref()
+    }
+
+    /// Takes the message out, leaving the accumulator empty.
+    pub fn take_message(&mut self) -> Option<AssistantMessage> {
+        let message = self.message.take();
+        *self = Self::default();
+        message
+    }
+
+    /// True once `message_stop` has arrived.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.stop_reason.as_deref()
+    }
+
+    pub fn stop_sequence(&self) -> Option<&str> {
+        self.stop_sequence.as_deref()
+    }
+
+    pub fn usage(&self) -> Option<&Usage> {
+        self.message.as_ref()?.usage.as_ref()
+    }
+
+    /// The tool input received so far for the block at `index`, until the block completes.
+    pub fn partial_json(&self, index: usize) -> Option<&str> {
+        self.partial_json.get(&index).map(String::as_str)
+    }
+
+    fn start_message(&mut self, event: &Value) -> Result<Option<MessageUpdate>, String> {
+        let message = &event["message"];
+        let usage = match message.get("usage") {
+            Some(Value::Object(usage)) => Some(merge_usage(Usage::default(), usage)?),
+            _ => None,
+        };
+        *self = Self::default();
+        self.stop_reason = message["stop_reason"].as_str().map(String::from);
+        self.message = Some(AssistantMessage {
+            content: Vec::new(),
+            model: message["model"].as_str().unwrap_or_default().to_string(),
+            parent_tool_use_id: None,
+            id: message["id"].as_str().map(String::from),
+            usage,
+        });
+        Ok(Some(MessageUpdate::MessageStarted))
+    }
+
+    fn start_block(&mut self, event: &Value) -> Result<Option<MessageUpdate>, String> {
+        let index = block_index(event)?;
+        let block = &event["content_block"];
+        let text = |key: &str| block[key].as_str().unwrap_or_default().to_string();
+        let block = match block["type"].as_str() {
+            Some("text") => ContentBlock::Text(TextBlock { text: text("text") }),
+            Some("thinking") => ContentBlock::Thinking(ThinkingBlock {
+                thinking: text("thinking"),
+                signature: text("signature"),
+            }),
+            Some("tool_use") => ContentBlock::ToolUse(ToolUseBlock {
+                id: text("id"),
+                name: text("name"),
+                input: HashMap::new(),
+            }),
+            _ => serde_json::from_value(block.clone())
+                .map_err(|e| format!("Invalid content block: {}", e))?,
+        };
+
+        let content = &mut self.in_progress()?.content;
+        match index.cmp(&content.len()) {
+            std::cmp::Ordering::Less => content[index] = block,
+            std::cmp::Ordering::Equal => content.push(block),
+            std::cmp::Ordering::Greater => {
+                return Err(format!(
+                    "content_block_start for index {} after {} blocks",
+                    index,
+                    content.len()
+                ))
+            }
+        }
+        Ok(Some(MessageUpdate::BlockStarted { index }))
+    }
+
+    fn apply_delta(&mut self, event: &Value) -> Result<Option<MessageUpdate>, String> {
+        let index = block_index(event)?;
+        let raw = &event["delta"];
+        let text = |key: &str| raw[key].as_str().unwrap_or_default().to_string();
+        let delta = match raw["type"].as_str() {
+            Some("text_delta") => Delta::Text(text("text")),
+            Some("thinking_delta") => Delta::Thinking(text("thinking")),
+            Some("input_json_delta") => Delta::InputJson(text("partial_json")),
+            Some("signature_delta") => Delta::Signature(text("signature")),
+            _ => Delta::Unknown { raw: raw.clone() },
+        };
+
+        match (&delta, self.block_mut(index)?) {
+            (Delta::Text(text), ContentBlock::Text(block)) => block.text.push_str(text),
+            (Delta::Thinking(thinking), ContentBlock::Thinking(block)) => {
+                block.thinking.push_str(thinking)
+            }
+            (Delta::Signature(signature), ContentBlock::Thinking(block)) => {
+                block.signature.push_str(signature)
+            }
+            // Tool input is only valid JSON once complete, so it is parsed at the stop
+            (Delta::InputJson(json), _) => {
+                self.partial_json.entry(index).or_default().push_str(json)
+            }
+            // Deltas for blocks this SDK keeps raw are passed through untouched
+            _ => {}
+        }
+        Ok(Some(MessageUpdate::BlockDelta { index, delta }))
+    }
+
+    fn stop_block(&mut self, event: &Value) -> Result<Option<MessageUpdate>, String> {
+        let index = block_index(event)?;
+        // An empty input streams as no JSON at all
+        let json = self
+            .partial_json
+            .remove(&index)
+            .filter(|json| !json.trim().is_empty());
+        let block = self.block_mut(index)?;
+        if let Some(json) = json {
+            let invalid = |e: serde_json::Error| format!("Invalid tool input {:?}: {}", json, e);
+            match block {
+                ContentBlock::ToolUse(tool_use) => {
+                    tool_use.input = serde_json::from_str(&json).map_err(invalid)?
+                }
+                ContentBlock::Unknown { raw } => {
+                    raw["input"] = serde_json::from_str(&json).map_err(invalid)?
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(MessageUpdate::BlockCompleted {
+            index,
+            block: block.clone(),
+        }))
+    }
+
+    fn apply_message_delta(&mut self, event: &Value) -> Result<Option<MessageUpdate>, String> {
+        let message = self.in_progress()?;
+        // `message_delta` usage holds running totals for the fields it includes
+        if let Some(Value::Object(delta_usage)) = event.get("usage") {
+            message.usage = Some(merge_usage(message.usage.unwrap_or_default(), delta_usage)?);
+        }
+        let delta = &event["delta"];
+        if let Some(stop_reason) = delta["stop_reason"].as_str() {
+            self.stop_reason = Some(stop_reason.to_string());
+        }
+        if let Some(stop_sequence) = delta["stop_sequence"].as_str() {
+            self.stop_sequence = Some(stop_sequence.to_string());
+        }
+        Ok(Some(MessageUpdate::MessageDelta {
+            stop_reason: self.stop_reason.clone(),
+        }))
+    }
+
+    fn in_progress(&mut self) -> Result<&mut AssistantMessage, String> {
+        self.message
+            .as_mut()
+            .ok_or_else(|| "Stream event before message_start".to_string())
+    }
+
+    fn block_mut(&mut self, index: usize) -> Result<&mut ContentBlock, String> {
+        self.in_progress()?
+            .content
+            .get_mut(index)
+            .ok_or_else(|| format!("Stream event for content block {} before it started", index))
+    }
+}
+
+// Overlays the non-null fields of an event's `usage` on `usage`
+fn merge_usage(usage: Usage, fields: &Map<String, Value>) -> Result<Usage, String> {
+    let mut merged = serde_json::to_value(usage).map_err(|e| e.to_string())?;
+    for (key, value) in fields {
+        if !value.is_null() {
+            merged[key] = value.clone();
+        }
+    }
+    serde_json::from_value(merged).map_err(|e| format!("Invalid usage: {}", e))
+}
+
+fn block_index(event: &Value) -> Result<usize, String> {
+    event["index"]
+        .as_u64()
+        .map(|index| index as usize)
+        .ok_or_else(|| "Stream event without a block index".to_string())
+}
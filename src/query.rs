@@ -1,7 +1,80 @@
-use crate::types::{Message, ToolDefinition};
+use crate::error::AgentError;
+use crate::internal::client::InternalClient;
+use crate::transport::SubprocessCLITransport;
+use crate::types::{ClaudeAgentOptions, Message, ToolDefinition, UserMessage};
+use futures::stream::{BoxStream, Stream};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Asks Claude one question and streams the messages of the answer.
+///
+/// Spawns the CLI configured from `options`, sends `prompt` (text, content
+/// blocks or a [`UserMessage`]) and yields every message up to and including
+/// the [`ResultMessage`](crate::types::ResultMessage). The CLI is shut down
+/// when the stream ends, and killed if the stream is dropped first.
+///
+/// ```no_run
+/// use claude_agent_sdk::{query, ClaudeAgentOptions, Message};
+/// use futures::StreamExt;
+///
+/// # async fn run() -> Result<(), claude_agent_sdk::AgentError> {
+/// let mut messages = query("What is the capital of France?", ClaudeAgentOptions::new()).await?;
+/// while let Some(message) = messages.next().await {
+///     if let Message::Result(result) = message? {
+///         println!("{}", result.result.unwrap_or_default());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn query(
+    prompt: impl Into<UserMessage>,
+    options: ClaudeAgentOptions,
+) -> Result<BoxStream<'static, Result<Message, AgentError>>, AgentError> {
+    let transport = Arc::new(SubprocessCLITransport::from_options_async(&options).await?);
+    InternalClient::new()
+        .process_query(prompt.into(), &options, transport)
+        .await
+}
+
+/// Like [`query`], but sends every message of `messages` as it is produced.
+///
+/// The CLI's input stays open while the stream is alive. Once it ends, the
+/// input is closed and the returned stream yields the remaining answers,
+/// ending after the last [`ResultMessage`](crate::types::ResultMessage).
+///
+/// ```no_run
+/// use claude_agent_sdk::{query_with_stream, ClaudeAgentOptions, Message, UserMessage};
+/// use futures::{stream, StreamExt};
+///
+/// # async fn run() -> Result<(), claude_agent_sdk::AgentError> {
+/// let prompts = stream::iter([
+///     UserMessage::text("What is 2 + 2?"),
+///     UserMessage::text("And times 3?"),
+/// ]);
+/// let mut messages = query_with_stream(prompts, ClaudeAgentOptions::new()).await?;
+/// while let Some(message) = messages.next().await {
+///     if let Message::Result(result) = message? {
+///         println!("{}", result.result.unwrap_or_default());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn query_with_stream<S>(
+    messages: S,
+    options: ClaudeAgentOptions,
+) -> Result<BoxStream<'static, Result<Message, AgentError>>, AgentError>
+where
+    S: Stream<Item = UserMessage> + Send + 'static,
+{
+    let transport = Arc::new(SubprocessCLITransport::from_options_async(&options).await?);
+    InternalClient::new()
+        .process_query_with_stream(messages, &options, transport)
+        .await
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct QueryRequest {
@@ -0,0 +1,183 @@
+//! JSON Schemas for tool inputs, built from Rust types.
+//!
+//! Implement [`InputSchema`] for a tool's input struct (or derive it with the
+//! `derive` feature) and the schema sent to Claude stays in sync with the type
+//! the handler deserializes:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use claude_agent_sdk::schema::InputSchema;
+//! use serde::Deserialize;
+//!
+//! /// Search the docs.
+//! #[derive(Deserialize, InputSchema)]
+//! struct SearchInput {
+//!     /// Words to look for
+//!     query: String,
+//!     /// Maximum number of hits
+//!     limit: Option<u32>,
+//! }
+//!
+//! let schema = SearchInput::input_schema();
+//! assert_eq!(schema["required"], serde_json::json!(["query"]));
+//! # }
+//! ```
+//!
+//! The derive reads doc comments as descriptions, leaves `Option` and
+//! `#[serde(default)]` fields out of `required`, honours `#[serde(rename)]`,
+//! `#[serde(rename_all)]`, `#[serde(skip)]` and `#[serde(flatten)]`, and turns
+//! enums of unit variants into string enums.
+
+use serde_json::{json, Map};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+pub use serde_json::Value;
+
+#[cfg(feature = "derive")]
+pub use claude_agent_sdk_macros::InputSchema;
+
+/// A type with a JSON Schema describing its JSON form.
+pub trait InputSchema {
+    /// Whether an object field of this type must be present. `Option` fields may be left out.
+    const REQUIRED: bool = true;
+
+    fn input_schema() -> Value;
+}
+
+/// Adds a `description` to a schema, as the derive does for doc comments.
+pub fn with_description(mut schema: Value, description: &str) -> Value {
+    if let Some(object) = schema.as_object_mut() {
+        object.insert("description".to_string(), json!(description));
+    }
+    schema
+}
+
+// Used by the derive: (name, schema, description, required) for each field
+#[doc(hidden)]
+pub fn object_schema(
+    description: Option<&str>,
+    fields: Vec<(&str, Value, Option<&str>, bool)>,
+) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (name, schema, field_description, is_required) in fields {
+        let schema = match field_description {
+            Some(text) => with_description(schema, text),
+            None => schema,
+        };
+        properties.insert(name.to_string(), schema);
+        if is_required {
+            required.push(json!(name));
+        }
+    }
+    let schema = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    });
+    match description {
+        Some(text) => with_description(schema, text),
+        None => schema,
+    }
+}
+
+// Used by the derive for `#[serde(flatten)]`: merges `inner`'s properties into
+// `schema`, and its required list too unless the flattened field is optional
+#[doc(hidden)]
+pub fn flatten_schema(mut schema: Value, inner: Value, is_required: bool) -> Value {
+    if let Some(properties) = inner.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            schema["properties"][name] = property.clone();
+        }
+    }
+    if is_required {
+        if let (Some(required), Some(inner_required)) = (
+            schema["required"].as_array_mut(),
+            inner.get("required").and_then(Value::as_array),
+        ) {
+            required.extend(inner_required.iter().cloned());
+        }
+    }
+    // A flattened map collects every other key
+    if let Some(additional) = inner.get("additionalProperties") {
+        schema["additionalProperties"] = additional.clone();
+    }
+    schema
+}
+
+#[doc(hidden)]
+pub fn string_enum_schema(description: Option<&str>, variants: &[&str]) -> Value {
+    let schema = json!({"type": "string", "enum": variants});
+    match description {
+        Some(text) => with_description(schema, text),
+        None => schema,
+    }
+}
+
+macro_rules! impl_input_schema {
+    ($schema:tt => $($ty:ty),+) => {
+        $(
+            impl InputSchema for $ty {
+                fn input_schema() -> Value {
+                    json!($schema)
+                }
+            }
+        )+
+    };
+}
+
+impl_input_schema!({"type": "string"} => String, char);
+impl_input_schema!({"type": "boolean"} => bool);
+impl_input_schema!({"type": "integer"} => i8, i16, i32, i64, i128, isize);
+impl_input_schema!({"type": "integer", "minimum": 0} => u8, u16, u32, u64, u128, usize);
+impl_input_schema!({"type": "number"} => f32, f64);
+impl_input_schema!({} => Value);
+
+impl<T: InputSchema> InputSchema for Option<T> {
+    const REQUIRED: bool = false;
+
+    fn input_schema() -> Value {
+        T::input_schema()
+    }
+}
+
+impl<T: InputSchema> InputSchema for Box<T> {
+    const REQUIRED: bool = T::REQUIRED;
+
+    fn input_schema() -> Value {
+        T::input_schema()
+    }
+}
+
+impl<T: InputSchema> InputSchema for Arc<T> {
+    const REQUIRED: bool = T::REQUIRED;
+
+    fn input_schema() -> Value {
+        T::input_schema()
+    }
+}
+
+impl<T: InputSchema> InputSchema for Vec<T> {
+    fn input_schema() -> Value {
+        json!({"type": "array", "items": T::input_schema()})
+    }
+}
+
+impl<T: InputSchema> InputSchema for HashSet<T> {
+    fn input_schema() -> Value {
+        json!({"type": "array", "items": T::input_schema(), "uniqueItems": true})
+    }
+}
+
+impl<T: InputSchema> InputSchema for HashMap<String, T> {
+    fn input_schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::input_schema()})
+    }
+}
+
+impl<T: InputSchema> InputSchema for BTreeMap<String, T> {
+    fn input_schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::input_schema()})
+    }
+}
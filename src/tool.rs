@@ -0,0 +1,200 @@
+//! Typed tools that can be served over MCP or run by a local agent loop.
+//!
+//! A [`Tool`] declares its input and output types; the framework deserializes
+//! the arguments Claude sent, runs the tool and turns the output, or the
+//! failure, into a result Claude sees. Bad input and tool errors come back as
+//! `is_error` results instead of aborting the turn.
+
+use crate::error::AgentError;
+use crate::mcp::{SdkMcpServer, SdkMcpTool, ToolResult};
+use crate::schema::InputSchema;
+use crate::types::{ToolDefinition, ToolResultBlock, ToolUseBlock};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// What a tool knows about the call it is answering.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolContext {
+    /// The id of the `tool_use` block being answered, when the caller has one.
+    pub tool_use_id: Option<String>,
+}
+
+/// A tool with typed input and output.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use claude_agent_sdk::error::AgentError;
+/// use claude_agent_sdk::schema::InputSchema;
+/// use claude_agent_sdk::tool::{Tool, ToolContext};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, InputSchema)]
+/// struct EchoInput {
+///     text: String,
+/// }
+///
+/// struct Echo;
+///
+/// #[async_trait::async_trait]
+/// impl Tool for Echo {
+///     type Input = EchoInput;
+///     type Output = String;
+///
+///     fn name(&self) -> &str {
+///         "echo"
+///     }
+///
+///     fn description(&self) -> &str {
+///         "Echoes its input"
+///     }
+///
+///     async fn call(&self, input: EchoInput, _ctx: ToolContext) -> Result<String, AgentError> {
+///         Ok(input.text)
+///     }
+/// }
+/// # }
+/// ```
+#[async_trait]
+pub trait Tool: Send + Sync + 'static {
+    type Input: InputSchema + DeserializeOwned + Send;
+    /// Converted with `Into<ToolResult>`: strings become text, [`Json`] becomes JSON text.
+    type Output: Into<ToolResult>;
+
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// An `Err` is reported to Claude as an error result.
+    async fn call(&self, input: Self::Input, ctx: ToolContext) -> Result<Self::Output, AgentError>;
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::for_input::<Self::Input>(self.name(), self.description())
+    }
+}
+
+/// Tool output sent to Claude as pretty-printed JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> From<Json<T>> for ToolResult {
+    fn from(Json(value): Json<T>) -> Self {
+        ToolResult::json(&value)
+    }
+}
+
+// Deserializes the arguments, runs the tool and folds every failure into the result
+pub(crate) async fn run<T: Tool + ?Sized>(
+    tool: &T,
+    arguments: Value,
+    ctx: ToolContext,
+) -> ToolResult {
+    let input = match serde_json::from_value::<T::Input>(arguments) {
+        Ok(input) => input,
+        Err(e) => {
+            return ToolResult::error(format!("Invalid input for tool '{}': {}", tool.name(), e))
+        }
+    };
+    match tool.call(input, ctx).await {
+        Ok(output) => output.into(),
+        Err(e) => ToolResult::error(e.to_string()),
+    }
+}
+
+type ErasedTool = Arc<dyn Fn(Value, ToolContext) -> BoxFuture<'static, ToolResult> + Send + Sync>;
+
+#[derive(Clone)]
+struct RegisteredTool {
+    definition: ToolDefinition,
+    run: ErasedTool,
+}
+
+/// A set of tools for an agent loop that runs tool calls itself.
+///
+/// The same tools can be served to the CLI with [`ToolRegistry::into_mcp_server`].
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<RegisteredTool>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tool, replacing any earlier tool with the same name.
+    pub fn register<T: Tool>(mut self, tool: T) -> Self {
+        let definition = tool.definition();
+        let tool = Arc::new(tool);
+        let run: ErasedTool = Arc::new(move |arguments, ctx| {
+            let tool = tool.clone();
+            Box::pin(async move { run(&*tool, arguments, ctx).await })
+        });
+        self.tools
+            .retain(|existing| existing.definition.name != definition.name);
+        self.tools.push(RegisteredTool { definition, run });
+        self
+    }
+
+    /// The definitions to advertise to the model, in registration order.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .iter()
+            .map(|tool| tool.definition.clone())
+            .collect()
+    }
+
+    /// Runs a tool by name. Unknown tools produce an error result.
+    pub async fn call(&self, name: &str, arguments: Value, ctx: ToolContext) -> ToolResult {
+        match self.tools.iter().find(|tool| tool.definition.name == name) {
+            Some(tool) => (tool.run)(arguments, ctx).await,
+            None => ToolResult::error(format!("Tool '{}' not found", name)),
+        }
+    }
+
+    /// Answers a `tool_use` block from an assistant message.
+    pub async fn run(&self, tool_use: &ToolUseBlock) -> ToolResultBlock {
+        let arguments = Value::Object(tool_use.input.clone().into_iter().collect());
+        let ctx = ToolContext {
+            tool_use_id: Some(tool_use.id.clone()),
+        };
+        self.call(&tool_use.name, arguments, ctx)
+            .await
+            .into_block(&tool_use.id)
+    }
+
+    /// Serves every registered tool from an in-process MCP server.
+    pub fn into_mcp_server(self, name: impl Into<String>) -> SdkMcpServer {
+        self.tools
+            .into_iter()
+            .fold(SdkMcpServer::new(name), |server, tool| {
+                let run = tool.run;
+                server.tool(SdkMcpTool::new(
+                    tool.definition.name,
+                    tool.definition.description,
+                    tool.definition.input_schema,
+                    move |arguments| {
+                        let result = run(arguments, ToolContext::default());
+                        async move { Ok(result.await) }
+                    },
+                ))
+            })
+    }
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self
+            .tools
+            .iter()
+            .map(|tool| tool.definition.name.as_str())
+            .collect();
+        f.debug_struct("ToolRegistry")
+            .field("tools", &names)
+            .finish()
+    }
+}
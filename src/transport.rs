@@ -1,22 +1,194 @@
+use crate::error::AgentError;
+use crate::types::{ClaudeAgentOptions, StderrCallback};
 use async_trait::async_trait;
 use serde_json::Value;
-use std::process::Stdio;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
-use tokio::process::Command;
+use tokio::process::{ChildStderr, Command};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Oldest Claude Code CLI release that speaks the protocol this SDK implements.
+pub const MINIMUM_CLAUDE_CODE_VERSION: &str = "2.0.0";
+
+/// Environment variable that points the SDK at a specific CLI binary.
+pub const CLI_PATH_ENV_VAR: &str = "CLAUDE_CODE_CLI_PATH";
+
+/// Number of stderr lines kept for error reports when the options do not say otherwise.
+pub const DEFAULT_STDERR_BUFFER_LINES: usize = 100;
+
+/// How long [`SubprocessCLITransport::close`] waits for the CLI to exit after stdin EOF.
+pub const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long [`check_claude_version`] waits for `claude --version` to answer.
+pub const DEFAULT_VERSION_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Environment variable that disables the `--version` check when set.
+pub const SKIP_VERSION_CHECK_ENV_VAR: &str = "CLAUDE_AGENT_SDK_SKIP_VERSION_CHECK";
+
+#[cfg(windows)]
+const CLI_NAMES: &[&str] = &["claude.exe", "claude.cmd"];
+#[cfg(not(windows))]
+const CLI_NAMES: &[&str] = &["claude"];
+
+/// Locates the Claude Code CLI.
+///
+/// Candidates are tried in order: `explicit`, the [`CLI_PATH_ENV_VAR`] override,
+/// every `PATH` entry, then the usual npm, yarn and local install locations.
+/// An explicit path or env override is authoritative: if it does not exist the
+/// search stops there instead of silently picking up another binary.
+pub fn find_claude_cli(explicit: Option<&Path>) -> Result<PathBuf, AgentError> {
+    let overridden = explicit
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os(CLI_PATH_ENV_VAR).map(PathBuf::from));
+    if let Some(path) = overridden {
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(AgentError::CLINotFound {
+                searched: vec![path],
+            })
+        };
+    }
+
+    let mut searched = Vec::new();
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            for name in CLI_NAMES {
+                searched.push(dir.join(name));
+            }
+        }
+    }
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        let home = PathBuf::from(home);
+        for dir in [
+            ".npm-global/bin",
+            ".local/bin",
+            "node_modules/.bin",
+            ".yarn/bin",
+            ".claude/local",
+        ] {
+            for name in CLI_NAMES {
+                searched.push(home.join(dir).join(name));
+            }
+        }
+    }
+    #[cfg(unix)]
+    searched.push(PathBuf::from("/usr/local/bin/claude"));
+
+    match searched.iter().find(|path| path.is_file()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(AgentError::CLINotFound { searched }),
+    }
+}
+
+/// Runs `cli --version` and checks it against [`MINIMUM_CLAUDE_CODE_VERSION`].
+///
+/// Returns the reported version on success. Blocks for at most
+/// [`DEFAULT_VERSION_CHECK_TIMEOUT`].
+pub fn check_claude_version(cli: &Path) -> Result<String, AgentError> {
+    check_claude_version_with_timeout(cli, DEFAULT_VERSION_CHECK_TIMEOUT)
+}
+
+/// Like [`check_claude_version`], killing `cli --version` if it has not
+/// finished within `timeout`.
+pub fn check_claude_version_with_timeout(
+    cli: &Path,
+    timeout: Duration,
+) -> Result<String, AgentError> {
+    let failed = |e: std::io::Error| {
+        AgentError::ProcessError(format!("Failed to run {} --version: {}", cli.display(), e))
+    };
+    let mut child = std::process::Command::new(cli)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(failed)?;
+    let deadline = std::time::Instant::now() + timeout;
+    while child.try_wait().map_err(failed)?.is_none() {
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(AgentError::ProcessError(format!(
+                "{} --version did not finish within {:?}",
+                cli.display(),
+                timeout
+            )));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let mut stdout = String::new();
+    if let Some(mut output) = child.stdout.take() {
+        std::io::Read::read_to_string(&mut output, &mut stdout).map_err(failed)?;
+    }
+    let found = stdout.trim().to_string();
+
+    let unsupported = || AgentError::UnsupportedCLIVersion {
+        path: cli.to_path_buf(),
+        found: found.clone(),
+        minimum: MINIMUM_CLAUDE_CODE_VERSION.to_string(),
+    };
+    let version = stdout
+        .split_whitespace()
+        .find_map(parse_version)
+        .ok_or_else(unsupported)?;
+    let minimum = parse_version(MINIMUM_CLAUDE_CODE_VERSION).expect("valid minimum version");
+    if version < minimum {
+        return Err(unsupported());
+    }
+    Ok(format!("{}.{}.{}", version.0, version.1, version.2))
+}
+
+fn parse_version(text: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = text.trim_start_matches('v').splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts
+        .next()?
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+    Some((major, minor, patch))
+}
 
 #[async_trait]
 pub trait Transport {
     async fn send(&self, payload: Value)
         -> Result<Value, Box<dyn std::error::Error + Send + Sync>>;
     async fn receive(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Tells the other side no more input is coming, without waiting for it to
+    /// exit. The default does nothing.
+    async fn end_input(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// Shuts the transport down. The default does nothing.
+    async fn close(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
 }
 
+/// Runs the CLI as a child process speaking newline-delimited JSON.
+///
+/// On Unix the CLI is started in its own process group, so closing or dropping
+/// the transport also takes down any tools the CLI launched.
 pub struct SubprocessCLITransport {
     child: Arc<Mutex<tokio::process::Child>>,
+    pid: Option<u32>,
     stdout_lines: Arc<Mutex<Lines<BufReader<tokio::process::ChildStdout>>>>,
-    stdin: Arc<Mutex<tokio::process::ChildStdin>>,
+    stdin: Arc<Mutex<Option<tokio::process::ChildStdin>>>,
+    stderr_lines: Arc<std::sync::Mutex<VecDeque<String>>>,
+    stderr_task: Mutex<Option<JoinHandle<()>>>,
+    closed: AtomicBool,
 }
 
 impl SubprocessCLITransport {
@@ -24,25 +196,171 @@ impl SubprocessCLITransport {
         command: &str,
         args: &[&str],
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let mut child = Command::new(command)
-            .args(args)
+        let mut command = Command::new(command);
+        command.args(args);
+        Ok(Self::spawn(command, None, DEFAULT_STDERR_BUFFER_LINES)?)
+    }
+
+    /// Spawns the `claude` CLI in stream-json mode, configured from `options`.
+    ///
+    /// The binary is located with [`find_claude_cli`] and its version is checked
+    /// with [`check_claude_version`] unless [`SKIP_VERSION_CHECK_ENV_VAR`] is set.
+    pub fn from_options(options: &ClaudeAgentOptions) -> Result<Self, AgentError> {
+        options.validate()?;
+        let cli = find_claude_cli(options.cli_path.as_deref())?;
+        if std::env::var_os(SKIP_VERSION_CHECK_ENV_VAR).is_none() {
+            check_claude_version(&cli)?;
+        }
+
+        let mut command = Command::new(cli);
+        command
+            .args(["--output-format", "stream-json", "--verbose"])
+            .args(options.to_cli_args())
+            .args(["--input-format", "stream-json"])
+            .envs(&options.env)
+            .env("CLAUDE_CODE_ENTRYPOINT", "sdk-rust");
+        if let Some(cwd) = &options.cwd {
+            command.current_dir(cwd);
+        }
+        Self::spawn(
+            command,
+            options.stderr.clone(),
+            options
+                .stderr_buffer_lines
+                .unwrap_or(DEFAULT_STDERR_BUFFER_LINES),
+        )
+    }
+
+    /// Runs [`from_options`](Self::from_options) on the blocking thread pool,
+    /// so locating the CLI and checking its version never stall the runtime.
+    pub async fn from_options_async(options: &ClaudeAgentOptions) -> Result<Self, AgentError> {
+        let options = options.clone();
+        tokio::task::spawn_blocking(move || Self::from_options(&options))
+            .await
+            .map_err(|e| AgentError::ProcessError(format!("Failed to start the CLI: {}", e)))?
+    }
+
+    fn spawn(
+        mut command: Command,
+        stderr_callback: Option<StderrCallback>,
+        stderr_buffer_lines: usize,
+    ) -> Result<Self, AgentError> {
+        #[cfg(unix)]
+        command.process_group(0);
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()?;
+        let pid = child.id();
 
-        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| AgentError::ProcessError("Failed to capture stdout".to_string()))?;
         let stdout_lines = BufReader::new(stdout).lines();
 
-        let stdin = child.stdin.take().ok_or("Failed to capture stdin")?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| AgentError::ProcessError("Failed to capture stdin".to_string()))?;
+
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| AgentError::ProcessError("Failed to capture stderr".to_string()))?;
+        let stderr_lines = Arc::new(std::sync::Mutex::new(VecDeque::new()));
+        let stderr_task = tokio::spawn(read_stderr(
+            stderr,
+            stderr_callback,
+            stderr_lines.clone(),
+            stderr_buffer_lines,
+        ));
 
         Ok(SubprocessCLITransport {
             child: Arc::new(Mutex::new(child)),
+            pid,
             stdout_lines: Arc::new(Mutex::new(stdout_lines)),
-            stdin: Arc::new(Mutex::new(stdin)),
+            stdin: Arc::new(Mutex::new(Some(stdin))),
+            stderr_lines,
+            stderr_task: Mutex::new(Some(stderr_task)),
+            closed: AtomicBool::new(false),
         })
     }
 
+    /// The most recent lines the CLI wrote to stderr, oldest first.
+    pub fn recent_stderr(&self) -> Vec<String> {
+        self.stderr_lines
+            .lock()
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The CLI's exit status, or `None` while it is still running.
+    ///
+    /// Use [`ExitStatus::code`] for the exit code and, on Unix,
+    /// `std::os::unix::process::ExitStatusExt::signal` for the terminating signal.
+    pub async fn exit_status(&self) -> Option<ExitStatus> {
+        self.child.lock().await.try_wait().ok().flatten()
+    }
+
+    /// Closes stdin, waits up to `timeout` for the CLI to exit, then kills its
+    /// process group. Returns how the CLI exited.
+    pub async fn close_with_timeout(&self, timeout: Duration) -> Result<ExitStatus, AgentError> {
+        // Dropping stdin sends EOF, which asks the CLI to finish up and exit.
+        self.stdin.lock().await.take();
+
+        let mut child = self.child.lock().await;
+        let status = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => {
+                self.kill_process_group();
+                child.kill().await?;
+                child.wait().await?
+            }
+        };
+        // The CLI is gone, but tools it started may still be running in its group.
+        self.kill_process_group();
+        self.closed.store(true, Ordering::SeqCst);
+        Ok(status)
+    }
+
+    fn kill_process_group(&self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            // SAFETY: killpg has no memory-safety preconditions; a stale group id just yields ESRCH.
+            unsafe {
+                libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+
+    async fn ended_error(&self) -> AgentError {
+        // Give the stderr reader a moment to drain what the process wrote before exiting.
+        if let Some(task) = self.stderr_task.lock().await.take() {
+            let _ = tokio::time::timeout(Duration::from_millis(500), task).await;
+        }
+        let status = {
+            let mut child = self.child.lock().await;
+            tokio::time::timeout(Duration::from_millis(500), child.wait())
+                .await
+                .ok()
+                .and_then(Result::ok)
+        };
+
+        let mut message = match status {
+            Some(status) => format!("Process ended ({}) before message was received", status),
+            None => "Process ended before message was received".to_string(),
+        };
+        let stderr = self.recent_stderr();
+        if !stderr.is_empty() {
+            message.push_str(". Recent stderr:\n");
+            message.push_str(&stderr.join("\n"));
+        }
+        AgentError::ProcessError(message)
+    }
+
     pub async fn is_alive(&self) -> bool {
         let mut child = self.child.lock().await;
         match child.try_wait() {
@@ -61,6 +379,9 @@ impl Transport for SubprocessCLITransport {
     ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
         let json_string = serde_json::to_string(&payload)?;
         let mut stdin = self.stdin.lock().await;
+        let stdin = stdin
+            .as_mut()
+            .ok_or_else(|| AgentError::ProcessError("CLI stdin is closed".to_string()))?;
         stdin.write_all(json_string.as_bytes()).await?;
         stdin.write_all(b"\n").await?;
         stdin.flush().await?;
@@ -69,11 +390,56 @@ impl Transport for SubprocessCLITransport {
 
     async fn receive(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
         let mut stdout_lines = self.stdout_lines.lock().await;
-        if let Some(line) = stdout_lines.next_line().await? {
+        while let Some(line) = stdout_lines.next_line().await? {
+            // Stray output from the CLI or its tools is not part of the protocol
             if let Ok(value) = serde_json::from_str::<Value>(&line) {
                 return Ok(value);
             }
         }
-        Err("Process ended before message was received".into())
+        Err(self.ended_error().await.into())
+    }
+
+    async fn end_input(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // The CLI finishes the queued turns and exits once stdin reaches EOF
+        self.stdin.lock().await.take();
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.close_with_timeout(DEFAULT_CLOSE_TIMEOUT).await?;
+        Ok(())
+    }
+}
+
+impl Drop for SubprocessCLITransport {
+    fn drop(&mut self) {
+        // `kill_on_drop` only reaches the CLI itself; take its tools down with it.
+        if !self.closed.load(Ordering::SeqCst) {
+            self.kill_process_group();
+        }
+    }
+}
+
+/// Drains the child's stderr so a chatty CLI can never block on a full pipe.
+async fn read_stderr(
+    stderr: ChildStderr,
+    callback: Option<StderrCallback>,
+    buffer: Arc<std::sync::Mutex<VecDeque<String>>>,
+    capacity: usize,
+) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(callback) = &callback {
+            callback(&line);
+        }
+        if capacity == 0 {
+            continue;
+        }
+        if let Ok(mut buffer) = buffer.lock() {
+            if buffer.len() == capacity {
+                buffer.pop_front();
+            }
+            buffer.push_back(line);
+        }
     }
 }
@@ -1,6 +1,19 @@
-use serde::{Deserialize, Serialize};
+use crate::error::AgentError;
+use crate::hooks::HookEvent;
+use crate::internal::query::{CanUseTool, HookMatcher, PermissionResult, ToolPermissionContext};
+use crate::mcp::{McpServerConfig, SdkMcpServer};
+use crate::schema::InputSchema;
+use crate::usage::{ModelUsage, Usage};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 // Content block types
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,31 +41,339 @@ pub struct ToolResultBlock {
     pub is_error: Option<bool>,
 }
 
+/// Where the bytes of an image or document come from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentSource {
+    Base64 {
+        media_type: String,
+        data: String,
+    },
+    Url {
+        url: String,
+    },
+    /// Plain-text document content. Only valid for documents.
+    Text {
+        media_type: String,
+        data: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageBlock {
+    pub source: ContentSource,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
+pub struct DocumentBlock {
+    pub source: ContentSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// A block of message content.
+///
+/// Block kinds this SDK does not know yet deserialize to `Unknown`, which keeps
+/// the raw JSON and serializes back to it unchanged.
+#[derive(Debug, Clone)]
 pub enum ContentBlock {
-    #[serde(rename = "text")]
     Text(TextBlock),
-    #[serde(rename = "thinking")]
     Thinking(ThinkingBlock),
-    #[serde(rename = "tool_use")]
     ToolUse(ToolUseBlock),
-    #[serde(rename = "tool_result")]
     ToolResult(ToolResultBlock),
+    Image(ImageBlock),
+    Document(DocumentBlock),
+    Unknown { raw: Value },
+}
+
+// The wire shape of the block kinds this SDK knows
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KnownContentBlock {
+    Text(TextBlock),
+    Thinking(ThinkingBlock),
+    ToolUse(ToolUseBlock),
+    ToolResult(ToolResultBlock),
+    Image(ImageBlock),
+    Document(DocumentBlock),
+}
+
+impl ContentBlock {
+    const KNOWN_TYPES: &'static [&'static str] = &[
+        "text",
+        "thinking",
+        "tool_use",
+        "tool_result",
+        "image",
+        "document",
+    ];
+
+    /// An image Claude fetches from a URL.
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentBlock::Image(ImageBlock {
+            source: ContentSource::Url { url: url.into() },
+        })
+    }
+
+    /// A document Claude fetches from a URL.
+    pub fn document_url(url: impl Into<String>) -> Self {
+        ContentBlock::Document(DocumentBlock {
+            source: ContentSource::Url { url: url.into() },
+            title: None,
+        })
+    }
+
+    /// Reads a PNG, JPEG, GIF or WebP file into a base64 image block.
+    ///
+    /// The type is taken from the file's contents, falling back to its
+    /// extension. Files over [`MAX_IMAGE_BYTES`] are rejected.
+    pub fn image_from_path(path: impl AsRef<Path>) -> Result<Self, AgentError> {
+        let path = path.as_ref();
+        let bytes = read_attachment(path, MAX_IMAGE_BYTES)?;
+        let media_type = sniff_media_type(&bytes)
+            .or_else(|| media_type_for_extension(path))
+            .filter(|media_type| media_type.starts_with("image/"))
+            .ok_or_else(|| invalid_attachment(path, "not a PNG, JPEG, GIF or WebP image"))?;
+        Ok(ContentBlock::Image(ImageBlock {
+            source: ContentSource::Base64 {
+                media_type: media_type.to_string(),
+                data: BASE64.encode(&bytes),
+            },
+        }))
+    }
+
+    /// Reads a PDF or plain-text file into a document block titled with the file name.
+    ///
+    /// PDFs are base64-encoded; text files are sent as text. Files over
+    /// [`MAX_DOCUMENT_BYTES`] are rejected.
+    pub fn document_from_path(path: impl AsRef<Path>) -> Result<Self, AgentError> {
+        let path = path.as_ref();
+        let bytes = read_attachment(path, MAX_DOCUMENT_BYTES)?;
+        let source = match sniff_media_type(&bytes).or_else(|| media_type_for_extension(path)) {
+            Some("application/pdf") => ContentSource::Base64 {
+                media_type: "application/pdf".to_string(),
+                data: BASE64.encode(&bytes),
+            },
+            Some("text/plain") => ContentSource::Text {
+                media_type: "text/plain".to_string(),
+                data: String::from_utf8(bytes)
+                    .map_err(|_| invalid_attachment(path, "text document is not valid UTF-8"))?,
+            },
+            _ => return Err(invalid_attachment(path, "not a PDF or plain-text document")),
+        };
+        Ok(ContentBlock::Document(DocumentBlock {
+            source,
+            title: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+        }))
+    }
+}
+
+/// The largest image file [`ContentBlock::image_from_path`] accepts (5 MiB).
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// The largest document file [`ContentBlock::document_from_path`] accepts (32 MiB).
+pub const MAX_DOCUMENT_BYTES: u64 = 32 * 1024 * 1024;
+
+fn read_attachment(path: &Path, max_bytes: u64) -> Result<Vec<u8>, AgentError> {
+    let size = std::fs::metadata(path)?.len();
+    if size > max_bytes {
+        return Err(invalid_attachment(
+            path,
+            &format!("{} bytes exceeds the {} byte limit", size, max_bytes),
+        ));
+    }
+    Ok(std::fs::read(path)?)
+}
+
+fn sniff_media_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'%', b'P', b'D', b'F', ..] => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+fn media_type_for_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "pdf" => Some("application/pdf"),
+        "txt" | "md" => Some("text/plain"),
+        _ => None,
+    }
+}
+
+fn invalid_attachment(path: &Path, reason: &str) -> AgentError {
+    AgentError::InvalidAttachment {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
+
+impl Serialize for ContentBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let known = match self {
+            ContentBlock::Text(block) => KnownContentBlock::Text(block.clone()),
+            ContentBlock::Thinking(block) => KnownContentBlock::Thinking(block.clone()),
+            ContentBlock::ToolUse(block) => KnownContentBlock::ToolUse(block.clone()),
+            ContentBlock::ToolResult(block) => KnownContentBlock::ToolResult(block.clone()),
+            ContentBlock::Image(block) => KnownContentBlock::Image(block.clone()),
+            ContentBlock::Document(block) => KnownContentBlock::Document(block.clone()),
+            ContentBlock::Unknown { raw } => return raw.serialize(serializer),
+        };
+        known.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentBlock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = Value::deserialize(deserializer)?;
+        let block_type = raw.get("type").and_then(Value::as_str);
+        if !block_type.is_some_and(|t| ContentBlock::KNOWN_TYPES.contains(&t)) {
+            return Ok(ContentBlock::Unknown { raw });
+        }
+        Ok(
+            match serde_json::from_value(raw).map_err(D::Error::custom)? {
+                KnownContentBlock::Text(block) => ContentBlock::Text(block),
+                KnownContentBlock::Thinking(block) => ContentBlock::Thinking(block),
+                KnownContentBlock::ToolUse(block) => ContentBlock::ToolUse(block),
+                KnownContentBlock::ToolResult(block) => ContentBlock::ToolResult(block),
+                KnownContentBlock::Image(block) => ContentBlock::Image(block),
+                KnownContentBlock::Document(block) => ContentBlock::Document(block),
+            },
+        )
+    }
+}
+
+/// The content of a user message: plain text or a list of content blocks.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum UserContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl UserContent {
+    /// The text of the message, with text blocks joined by newlines.
+    pub fn text(&self) -> String {
+        match self {
+            UserContent::Text(text) => text.clone(),
+            UserContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// The content as blocks; text becomes a single text block.
+    pub fn blocks(&self) -> Vec<ContentBlock> {
+        match self {
+            UserContent::Text(text) => vec![ContentBlock::Text(TextBlock { text: text.clone() })],
+            UserContent::Blocks(blocks) => blocks.clone(),
+        }
+    }
+}
+
+impl From<String> for UserContent {
+    fn from(text: String) -> Self {
+        UserContent::Text(text)
+    }
+}
+
+impl From<&str> for UserContent {
+    fn from(text: &str) -> Self {
+        UserContent::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentBlock>> for UserContent {
+    fn from(blocks: Vec<ContentBlock>) -> Self {
+        UserContent::Blocks(blocks)
+    }
 }
 
 // Message types
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserMessage {
-    pub content: String,
+    pub content: UserContent,
     pub parent_tool_use_id: Option<String>,
 }
 
+impl UserMessage {
+    pub fn text(text: impl Into<String>) -> Self {
+        UserMessage {
+            content: UserContent::Text(text.into()),
+            parent_tool_use_id: None,
+        }
+    }
+
+    pub fn with_blocks(blocks: Vec<ContentBlock>) -> Self {
+        UserMessage {
+            content: UserContent::Blocks(blocks),
+            parent_tool_use_id: None,
+        }
+    }
+
+    /// Answers a `tool_use` block. `content` is a string or a list of content blocks.
+    pub fn tool_result(
+        tool_use_id: impl Into<String>,
+        content: impl Into<Value>,
+        is_error: bool,
+    ) -> Self {
+        Self::with_blocks(vec![ContentBlock::ToolResult(ToolResultBlock {
+            tool_use_id: tool_use_id.into(),
+            content: Some(content.into()),
+            is_error: Some(is_error),
+        })])
+    }
+
+    pub fn parent_tool_use_id(mut self, parent_tool_use_id: impl Into<String>) -> Self {
+        self.parent_tool_use_id = Some(parent_tool_use_id.into());
+        self
+    }
+
+    /// The frame the CLI reads on stdin with `--input-format stream-json`.
+    pub fn to_input_frame(&self, session_id: &str) -> Value {
+        serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": self.content},
+            "parent_tool_use_id": self.parent_tool_use_id,
+            "session_id": session_id,
+        })
+    }
+}
+
+impl<T: Into<UserContent>> From<T> for UserMessage {
+    fn from(content: T) -> Self {
+        UserMessage {
+            content: content.into(),
+            parent_tool_use_id: None,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AssistantMessage {
     pub content: Vec<ContentBlock>,
     pub model: String,
     pub parent_tool_use_id: Option<String>,
+    /// The API message id; several assistant messages can share one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -70,7 +391,14 @@ pub struct ResultMessage {
     pub num_turns: u32,
     pub session_id: String,
     pub total_cost_usd: Option<f64>,
-    pub usage: Option<HashMap<String, Value>>,
+    pub usage: Option<Usage>,
+    /// Usage per model, keyed by model name.
+    #[serde(
+        rename = "modelUsage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub model_usage: Option<HashMap<String, ModelUsage>>,
     pub result: Option<String>,
 }
 
@@ -82,29 +410,102 @@ pub struct StreamEvent {
     pub parent_tool_use_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
+/// A message in a conversation.
+///
+/// Message types this SDK does not know yet become `Unknown`, keeping the
+/// `type` tag and the raw JSON, so newer CLI versions do not break parsing.
+#[derive(Debug, Clone)]
 pub enum Message {
-    #[serde(rename = "user")]
     User(UserMessage),
-    #[serde(rename = "assistant")]
     Assistant(AssistantMessage),
-    #[serde(rename = "system")]
     System(SystemMessage),
-    #[serde(rename = "result")]
     Result(ResultMessage),
-    #[serde(rename = "stream_event")]
     StreamEvent(StreamEvent),
-    #[serde(rename = "text")]
     Text(TextMessage),
-    #[serde(rename = "tool_use")]
     ToolUse(ToolUseMessage),
-    #[serde(rename = "tool_result")]
     ToolResult(ToolResultMessage),
-    #[serde(rename = "partial_text")]
     PartialText(PartialTextMessage),
-    #[serde(rename = "partial_tool_use")]
     PartialToolUse(PartialToolUseMessage),
+    Unknown { type_name: String, raw: Value },
+}
+
+// The wire shape of the message types this SDK knows
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KnownMessage {
+    User(UserMessage),
+    Assistant(AssistantMessage),
+    System(SystemMessage),
+    Result(ResultMessage),
+    StreamEvent(StreamEvent),
+    Text(TextMessage),
+    ToolUse(ToolUseMessage),
+    ToolResult(ToolResultMessage),
+    PartialText(PartialTextMessage),
+    PartialToolUse(PartialToolUseMessage),
+}
+
+impl Message {
+    const KNOWN_TYPES: &'static [&'static str] = &[
+        "user",
+        "assistant",
+        "system",
+        "result",
+        "stream_event",
+        "text",
+        "tool_use",
+        "tool_result",
+        "partial_text",
+        "partial_tool_use",
+    ];
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let known = match self {
+            Message::User(message) => KnownMessage::User(message.clone()),
+            Message::Assistant(message) => KnownMessage::Assistant(message.clone()),
+            Message::System(message) => KnownMessage::System(message.clone()),
+            Message::Result(message) => KnownMessage::Result(message.clone()),
+            Message::StreamEvent(message) => KnownMessage::StreamEvent(message.clone()),
+            Message::Text(message) => KnownMessage::Text(message.clone()),
+            Message::ToolUse(message) => KnownMessage::ToolUse(message.clone()),
+            Message::ToolResult(message) => KnownMessage::ToolResult(message.clone()),
+            Message::PartialText(message) => KnownMessage::PartialText(message.clone()),
+            Message::PartialToolUse(message) => KnownMessage::PartialToolUse(message.clone()),
+            Message::Unknown { raw, .. } => return raw.serialize(serializer),
+        };
+        known.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = Value::deserialize(deserializer)?;
+        let Some(type_name) = raw.get("type").and_then(Value::as_str) else {
+            return Err(D::Error::custom("message is missing a 'type' field"));
+        };
+        if !Message::KNOWN_TYPES.contains(&type_name) {
+            return Ok(Message::Unknown {
+                type_name: type_name.to_string(),
+                raw,
+            });
+        }
+        Ok(
+            match serde_json::from_value(raw).map_err(D::Error::custom)? {
+                KnownMessage::User(message) => Message::User(message),
+                KnownMessage::Assistant(message) => Message::Assistant(message),
+                KnownMessage::System(message) => Message::System(message),
+                KnownMessage::Result(message) => Message::Result(message),
+                KnownMessage::StreamEvent(message) => Message::StreamEvent(message),
+                KnownMessage::Text(message) => Message::Text(message),
+                KnownMessage::ToolUse(message) => Message::ToolUse(message),
+                KnownMessage::ToolResult(message) => Message::ToolResult(message),
+                KnownMessage::PartialText(message) => Message::PartialText(message),
+                KnownMessage::PartialToolUse(message) => Message::PartialToolUse(message),
+            },
+        )
+    }
 }
 
 impl Message {
@@ -120,6 +521,7 @@ impl Message {
             Message::ToolResult(msg) => &msg.message_id,
             Message::PartialText(msg) => &msg.message_id,
             Message::PartialToolUse(msg) => &msg.message_id,
+            Message::Unknown { .. } => "unknown_message",
         }
     }
 }
@@ -179,3 +581,501 @@ pub struct ToolDefinition {
     pub description: String,
     pub input_schema: Value,
 }
+
+impl ToolDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+    ) -> Self {
+        ToolDefinition {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+        }
+    }
+
+    /// A definition whose schema comes from the tool's input type.
+    pub fn for_input<T: InputSchema>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self::new(name, description, T::input_schema())
+    }
+}
+
+// Agent options
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    #[default]
+    Default,
+    AcceptEdits,
+    Plan,
+    BypassPermissions,
+}
+
+impl PermissionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionMode::Default => "default",
+            PermissionMode::AcceptEdits => "acceptEdits",
+            PermissionMode::Plan => "plan",
+            PermissionMode::BypassPermissions => "bypassPermissions",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    User,
+    Project,
+    Local,
+}
+
+impl SettingSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettingSource::User => "user",
+            SettingSource::Project => "project",
+            SettingSource::Local => "local",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SystemPrompt {
+    /// Replaces the default Claude Code system prompt entirely.
+    Text(String),
+    /// Keeps the named preset (currently only `claude_code`), optionally appending to it.
+    Preset {
+        preset: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        append: Option<String>,
+    },
+}
+
+impl From<&str> for SystemPrompt {
+    fn from(text: &str) -> Self {
+        SystemPrompt::Text(text.to_string())
+    }
+}
+
+impl From<String> for SystemPrompt {
+    fn from(text: String) -> Self {
+        SystemPrompt::Text(text)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AgentDefinition {
+    pub description: String,
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl AgentDefinition {
+    pub fn new(description: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            prompt: prompt.into(),
+            tools: None,
+            model: None,
+        }
+    }
+
+    pub fn tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+}
+
+/// Receives every line the CLI writes to stderr.
+pub type StderrCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Options used to launch and configure the Claude Code CLI.
+///
+/// Build it with the chained setters and hand it to
+/// [`SubprocessCLITransport::from_options`](crate::transport::SubprocessCLITransport::from_options),
+/// which compiles it into the matching `claude` command line.
+#[derive(Clone, Default)]
+pub struct ClaudeAgentOptions {
+    pub model: Option<String>,
+    pub max_turns: Option<u32>,
+    pub allowed_tools: Vec<String>,
+    pub disallowed_tools: Vec<String>,
+    pub system_prompt: Option<SystemPrompt>,
+    pub permission_mode: Option<PermissionMode>,
+    pub permission_prompt_tool_name: Option<String>,
+    /// Asked before each tool use; the CLI sends these requests over the control protocol.
+    pub can_use_tool: Option<CanUseTool>,
+    /// Callbacks the CLI runs at hook events, registered during initialize.
+    pub hooks: HashMap<HookEvent, Vec<HookMatcher>>,
+    pub continue_conversation: bool,
+    pub resume: Option<String>,
+    pub fork_session: bool,
+    pub settings: Option<String>,
+    pub add_dirs: Vec<PathBuf>,
+    pub cwd: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+    pub setting_sources: Option<Vec<SettingSource>>,
+    pub mcp_servers: HashMap<String, McpServerConfig>,
+    pub agents: HashMap<String, AgentDefinition>,
+    pub include_partial_messages: bool,
+    /// Arbitrary CLI flags, passed as `--flag value` (or just `--flag` when the value is `None`).
+    pub extra_args: BTreeMap<String, Option<String>>,
+    /// Explicit path to the `claude` binary; skips discovery when set.
+    pub cli_path: Option<PathBuf>,
+    pub stderr: Option<StderrCallback>,
+    /// How many recent stderr lines to keep for error reports (defaults to 100).
+    pub stderr_buffer_lines: Option<usize>,
+}
+
+impl fmt::Debug for ClaudeAgentOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClaudeAgentOptions")
+            .field("model", &self.model)
+            .field("max_turns", &self.max_turns)
+            .field("allowed_tools", &self.allowed_tools)
+            .field("disallowed_tools", &self.disallowed_tools)
+            .field("system_prompt", &self.system_prompt)
+            .field("permission_mode", &self.permission_mode)
+            .field(
+                "permission_prompt_tool_name",
+                &self.permission_prompt_tool_name,
+            )
+            .field(
+                "can_use_tool",
+                &self.can_use_tool.as_ref().map(|_| "<callback>"),
+            )
+            .field("hooks", &self.hooks)
+            .field("continue_conversation", &self.continue_conversation)
+            .field("resume", &self.resume)
+            .field("fork_session", &self.fork_session)
+            .field("settings", &self.settings)
+            .field("add_dirs", &self.add_dirs)
+            .field("cwd", &self.cwd)
+            .field("env", &self.env)
+            .field("setting_sources", &self.setting_sources)
+            .field("mcp_servers", &self.mcp_servers)
+            .field("agents", &self.agents)
+            .field("include_partial_messages", &self.include_partial_messages)
+            .field("extra_args", &self.extra_args)
+            .field("cli_path", &self.cli_path)
+            .field("stderr", &self.stderr.as_ref().map(|_| "<callback>"))
+            .field("stderr_buffer_lines", &self.stderr_buffer_lines)
+            .finish()
+    }
+}
+
+impl ClaudeAgentOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn max_turns(mut self, max_turns: u32) -> Self {
+        self.max_turns = Some(max_turns);
+        self
+    }
+
+    pub fn allowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn disallowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.disallowed_tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn system_prompt(mut self, system_prompt: impl Into<SystemPrompt>) -> Self {
+        self.system_prompt = Some(system_prompt.into());
+        self
+    }
+
+    pub fn permission_mode(mut self, mode: PermissionMode) -> Self {
+        self.permission_mode = Some(mode);
+        self
+    }
+
+    pub fn permission_prompt_tool_name(mut self, tool_name: impl Into<String>) -> Self {
+        self.permission_prompt_tool_name = Some(tool_name.into());
+        self
+    }
+
+    /// Lets `callback` allow, deny or rewrite each tool use.
+    ///
+    /// The CLI is started with `--permission-prompt-tool stdio` so it asks the
+    /// SDK, which rules out [`permission_prompt_tool_name`](Self::permission_prompt_tool_name).
+    pub fn can_use_tool<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn(String, HashMap<String, Value>, ToolPermissionContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<PermissionResult, AgentError>> + Send + 'static,
+    {
+        self.can_use_tool = Some(Arc::new(move |tool_name, input, context| {
+            Box::pin(callback(tool_name, input, context))
+        }));
+        self
+    }
+
+    /// Runs `matcher`'s callbacks when the CLI reaches `event`.
+    pub fn hook(mut self, event: HookEvent, matcher: HookMatcher) -> Self {
+        self.hooks.entry(event).or_default().push(matcher);
+        self
+    }
+
+    pub fn continue_conversation(mut self, continue_conversation: bool) -> Self {
+        self.continue_conversation = continue_conversation;
+        self
+    }
+
+    pub fn resume(mut self, session_id: impl Into<String>) -> Self {
+        self.resume = Some(session_id.into());
+        self
+    }
+
+    pub fn fork_session(mut self, fork_session: bool) -> Self {
+        self.fork_session = fork_session;
+        self
+    }
+
+    pub fn settings(mut self, settings: impl Into<String>) -> Self {
+        self.settings = Some(settings.into());
+        self
+    }
+
+    pub fn add_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.add_dirs.push(dir.into());
+        self
+    }
+
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn setting_sources<I>(mut self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = SettingSource>,
+    {
+        self.setting_sources = Some(sources.into_iter().collect());
+        self
+    }
+
+    pub fn mcp_server(
+        mut self,
+        name: impl Into<String>,
+        config: impl Into<McpServerConfig>,
+    ) -> Self {
+        self.mcp_servers.insert(name.into(), config.into());
+        self
+    }
+
+    /// Adds several servers at once, e.g. from [`McpServerConfig::load_file`].
+    pub fn mcp_servers<I>(mut self, servers: I) -> Self
+    where
+        I: IntoIterator<Item = (String, McpServerConfig)>,
+    {
+        self.mcp_servers.extend(servers);
+        self
+    }
+
+    /// The in-process servers among `mcp_servers`, keyed by the name the CLI will use.
+    pub fn sdk_mcp_servers(&self) -> HashMap<String, Arc<SdkMcpServer>> {
+        self.mcp_servers
+            .iter()
+            .filter_map(|(name, config)| match config {
+                McpServerConfig::Sdk(server) => Some((name.clone(), server.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Rejects options the CLI would choke on before any process is started.
+    pub fn validate(&self) -> Result<(), AgentError> {
+        if self.can_use_tool.is_some() && self.permission_prompt_tool_name.is_some() {
+            return Err(AgentError::InvalidOptions(
+                "can_use_tool cannot be combined with permission_prompt_tool_name".to_string(),
+            ));
+        }
+        for (name, config) in &self.mcp_servers {
+            config.validate(name)?;
+        }
+        Ok(())
+    }
+
+    pub fn agent(mut self, name: impl Into<String>, agent: AgentDefinition) -> Self {
+        self.agents.insert(name.into(), agent);
+        self
+    }
+
+    pub fn include_partial_messages(mut self, include: bool) -> Self {
+        self.include_partial_messages = include;
+        self
+    }
+
+    pub fn extra_arg(mut self, flag: impl Into<String>, value: Option<String>) -> Self {
+        self.extra_args.insert(flag.into(), value);
+        self
+    }
+
+    pub fn cli_path(mut self, cli_path: impl Into<PathBuf>) -> Self {
+        self.cli_path = Some(cli_path.into());
+        self
+    }
+
+    pub fn stderr<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.stderr = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn stderr_buffer_lines(mut self, lines: usize) -> Self {
+        self.stderr_buffer_lines = Some(lines);
+        self
+    }
+
+    /// Compiles the options into `claude` command line flags.
+    ///
+    /// `cwd` and `env` are not part of the command line; the transport applies
+    /// them to the spawned process instead.
+    pub fn to_cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        match &self.system_prompt {
+            Some(SystemPrompt::Text(text)) => {
+                args.push("--system-prompt".to_string());
+                args.push(text.clone());
+            }
+            Some(SystemPrompt::Preset {
+                append: Some(append),
+                ..
+            }) => {
+                args.push("--append-system-prompt".to_string());
+                args.push(append.clone());
+            }
+            Some(SystemPrompt::Preset { append: None, .. }) | None => {}
+        }
+
+        if !self.allowed_tools.is_empty() {
+            args.push("--allowedTools".to_string());
+            args.push(self.allowed_tools.join(","));
+        }
+
+        if let Some(max_turns) = self.max_turns {
+            args.push("--max-turns".to_string());
+            args.push(max_turns.to_string());
+        }
+
+        if !self.disallowed_tools.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.push(self.disallowed_tools.join(","));
+        }
+
+        if let Some(model) = &self.model {
+            args.push("--model".to_string());
+            args.push(model.clone());
+        }
+
+        // `stdio` routes permission prompts to `can_use_tool` over the control protocol
+        let permission_prompt_tool = match &self.permission_prompt_tool_name {
+            Some(tool_name) => Some(tool_name.as_str()),
+            None => self.can_use_tool.as_ref().map(|_| "stdio"),
+        };
+        if let Some(tool_name) = permission_prompt_tool {
+            args.push("--permission-prompt-tool".to_string());
+            args.push(tool_name.to_string());
+        }
+
+        if let Some(mode) = self.permission_mode {
+            args.push("--permission-mode".to_string());
+            args.push(mode.as_str().to_string());
+        }
+
+        if self.continue_conversation {
+            args.push("--continue".to_string());
+        }
+
+        if let Some(session_id) = &self.resume {
+            args.push("--resume".to_string());
+            args.push(session_id.clone());
+        }
+
+        if let Some(settings) = &self.settings {
+            args.push("--settings".to_string());
+            args.push(settings.clone());
+        }
+
+        for dir in &self.add_dirs {
+            args.push("--add-dir".to_string());
+            args.push(dir.display().to_string());
+        }
+
+        if !self.mcp_servers.is_empty() {
+            args.push("--mcp-config".to_string());
+            args.push(serde_json::json!({ "mcpServers": self.mcp_servers }).to_string());
+        }
+
+        if self.include_partial_messages {
+            args.push("--include-partial-messages".to_string());
+        }
+
+        if self.fork_session {
+            args.push("--fork-session".to_string());
+        }
+
+        if !self.agents.is_empty() {
+            args.push("--agents".to_string());
+            args.push(serde_json::json!(self.agents).to_string());
+        }
+
+        if let Some(sources) = &self.setting_sources {
+            let sources: Vec<&str> = sources.iter().map(SettingSource::as_str).collect();
+            args.push("--setting-sources".to_string());
+            args.push(sources.join(","));
+        }
+
+        for (flag, value) in &self.extra_args {
+            args.push(format!("--{}", flag));
+            if let Some(value) = value {
+                args.push(value.clone());
+            }
+        }
+
+        args
+    }
+}
@@ -0,0 +1,282 @@
+//! Token usage reported by the CLI, and totals across turns and sessions.
+//!
+//! Assistant messages carry the usage of the API call that produced them and
+//! result messages carry the usage of the whole query, along with the cost
+//! and per-model usage of the session so far. [`UsageTracker`] adds these up
+//! for cost reporting and chargeback:
+//!
+//! ```
+//! use claude_agent_sdk::usage::{Usage, UsageTracker};
+//!
+//! let mut tracker = UsageTracker::new();
+//! // for each message received: tracker.record(&message);
+//! assert_eq!(tracker.total(), Usage::default());
+//! ```
+
+use crate::types::{AssistantMessage, Message, ResultMessage};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// Requests served by server-side tools such as web search.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ServerToolUse {
+    #[serde(deserialize_with = "null_as_default")]
+    pub web_search_requests: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub web_fetch_requests: u64,
+}
+
+/// Token counts in the shape of the Messages API `usage` object.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Usage {
+    #[serde(deserialize_with = "null_as_default")]
+    pub input_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub output_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub cache_creation_input_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub cache_read_input_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub server_tool_use: ServerToolUse,
+}
+
+impl Usage {
+    /// Input tokens including those written to and read from the prompt cache.
+    pub fn total_input_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.total_input_tokens() + self.output_tokens
+    }
+}
+
+/// One model's share of a result's usage, as reported in `modelUsage`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ModelUsage {
+    #[serde(deserialize_with = "null_as_default")]
+    pub input_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub output_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub cache_creation_input_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub cache_read_input_tokens: u64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub web_search_requests: u64,
+    #[serde(rename = "costUSD")]
+    #[serde(deserialize_with = "null_as_default")]
+    pub cost_usd: f64,
+    #[serde(deserialize_with = "null_as_default")]
+    pub context_window: u64,
+}
+
+// The API sends `null` for counts that do not apply, such as cache tokens
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+impl ModelUsage {
+    pub fn usage(&self) -> Usage {
+        Usage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_creation_input_tokens: self.cache_creation_input_tokens,
+            cache_read_input_tokens: self.cache_read_input_tokens,
+            server_tool_use: ServerToolUse {
+                web_search_requests: self.web_search_requests,
+                web_fetch_requests: 0,
+            },
+        }
+    }
+}
+
+impl Add for ServerToolUse {
+    type Output = ServerToolUse;
+
+    fn add(self, other: ServerToolUse) -> ServerToolUse {
+        ServerToolUse {
+            web_search_requests: self.web_search_requests + other.web_search_requests,
+            web_fetch_requests: self.web_fetch_requests + other.web_fetch_requests,
+        }
+    }
+}
+
+impl Add for Usage {
+    type Output = Usage;
+
+    fn add(self, other: Usage) -> Usage {
+        Usage {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+            cache_creation_input_tokens: self.cache_creation_input_tokens
+                + other.cache_creation_input_tokens,
+            cache_read_input_tokens: self.cache_read_input_tokens + other.cache_read_input_tokens,
+            server_tool_use: self.server_tool_use + other.server_tool_use,
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Usage {
+    fn sum<I: Iterator<Item = Usage>>(iter: I) -> Usage {
+        iter.fold(Usage::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Usage> for Usage {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Usage {
+        iter.copied().sum()
+    }
+}
+
+impl Add for ModelUsage {
+    type Output = ModelUsage;
+
+    fn add(mut self, other: ModelUsage) -> ModelUsage {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for ModelUsage {
+    fn add_assign(&mut self, other: ModelUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.web_search_requests += other.web_search_requests;
+        self.cost_usd += other.cost_usd;
+        // A window size, not a count
+        self.context_window = self.context_window.max(other.context_window);
+    }
+}
+
+impl Sum for ModelUsage {
+    fn sum<I: Iterator<Item = ModelUsage>>(iter: I) -> ModelUsage {
+        iter.fold(ModelUsage::default(), Add::add)
+    }
+}
+
+/// Usage and cost attributed to one session.
+///
+/// `usage` and `num_turns` add up every result; `cost_usd` and `by_model` are
+/// the session totals from the latest one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionUsage {
+    pub usage: Usage,
+    pub cost_usd: f64,
+    pub num_turns: u32,
+    pub by_model: BTreeMap<String, ModelUsage>,
+}
+
+/// Totals usage across turns and sessions.
+///
+/// Result messages are authoritative. Their `usage` and `num_turns` cover the
+/// query that produced them and are added up per session, while
+/// `total_cost_usd` and `modelUsage` are running totals for the session, so
+/// the latest result's values replace the earlier ones. Assistant messages
+/// seen since the last result are added on top as a running estimate, counted
+/// once per API message id because the CLI repeats the same usage on every
+/// message of a multi-block response.
+#[derive(Debug, Clone, Default)]
+pub struct UsageTracker {
+    sessions: BTreeMap<String, SessionUsage>,
+    pending: Usage,
+    pending_ids: HashSet<String>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records any message; only assistant and result messages carry usage.
+    pub fn record(&mut self, message: &Message) {
+        match message {
+            Message::Assistant(assistant) => self.record_assistant(assistant),
+            Message::Result(result) => self.record_result(result),
+            _ => {}
+        }
+    }
+
+    pub fn record_assistant(&mut self, message: &AssistantMessage) {
+        let Some(usage) = message.usage else {
+            return;
+        };
+        if let Some(id) = &message.id {
+            if !self.pending_ids.insert(id.clone()) {
+                return;
+            }
+        }
+        self.pending += usage;
+    }
+
+    /// Adds a result's usage to its session, takes over the session's cost
+    /// and per-model totals, and drops the running estimate it replaces.
+    pub fn record_result(&mut self, message: &ResultMessage) {
+        let session = self.sessions.entry(message.session_id.clone()).or_default();
+        session.usage += message.usage.unwrap_or_default();
+        session.num_turns += message.num_turns;
+        if let Some(cost) = message.total_cost_usd {
+            session.cost_usd = cost;
+        }
+        if let Some(model_usage) = &message.model_usage {
+            session.by_model = model_usage
+                .iter()
+                .map(|(model, usage)| (model.clone(), usage.clone()))
+                .collect();
+        }
+        self.pending = Usage::default();
+        self.pending_ids.clear();
+    }
+
+    /// Usage across every session, including assistant messages not yet followed by a result.
+    pub fn total(&self) -> Usage {
+        self.sessions
+            .values()
+            .map(|session| session.usage)
+            .sum::<Usage>()
+            + self.pending
+    }
+
+    /// Cost across every session, as reported by result messages.
+    pub fn total_cost_usd(&self) -> f64 {
+        self.sessions.values().map(|session| session.cost_usd).sum()
+    }
+
+    /// Usage per model across every session.
+    pub fn by_model(&self) -> BTreeMap<String, ModelUsage> {
+        let mut totals: BTreeMap<String, ModelUsage> = BTreeMap::new();
+        for session in self.sessions.values() {
+            for (model, usage) in &session.by_model {
+                *totals.entry(model.clone()).or_default() += usage.clone();
+            }
+        }
+        totals
+    }
+
+    pub fn session(&self, session_id: &str) -> Option<&SessionUsage> {
+        self.sessions.get(session_id)
+    }
+
+    /// Every session seen so far, by session id.
+    pub fn sessions(&self) -> &BTreeMap<String, SessionUsage> {
+        &self.sessions
+    }
+}
@@ -9,6 +9,13 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+// Also declared by tests/mod.rs when every test file is built as one crate
+#[allow(clippy::duplicate_mod)]
+#[path = "test_utils.rs"]
+mod test_utils;
+
+use test_utils::{FakeCliTransport, SharedFakeCli};
+
 // Mock transport for testing
 mock! {
     pub Transport {}
@@ -19,15 +26,62 @@ mock! {
     }
 }
 
+// Never answers `receive`, like a CLI that is still thinking
+struct SilentTransport;
+
+#[async_trait::async_trait]
+impl Transport for SilentTransport {
+    async fn send(
+        &self,
+        _payload: Value,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Value::Null)
+    }
+
+    async fn receive(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        std::future::pending().await
+    }
+}
+
+// A transport that replays `frames`, then reports that the CLI exited
+fn replaying_client(frames: Vec<Value>) -> Client {
+    let frames = std::sync::Mutex::new(std::collections::VecDeque::from(frames));
+    let mut mock_transport = MockTransport::new();
+    mock_transport.expect_receive().returning(move || {
+        frames
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| "CLI exited".into())
+    });
+    Client::new(Arc::new(Mutex::new(mock_transport)) as Arc<Mutex<dyn Transport>>)
+}
+
+fn result_frame() -> Value {
+    json!({
+        "type": "result", "subtype": "success", "duration_ms": 1, "duration_api_ms": 1,
+        "is_error": false, "num_turns": 1, "session_id": "s1"
+    })
+}
+
+fn assistant_frame(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "m", "content": [{"type": "text", "text": text}]}
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use claude_agent_sdk::ClaudeSDKClient;
+    use futures::StreamExt;
 
     #[tokio::test]
     async fn test_client_creation() {
         let mock_transport = MockTransport::new();
         let transport_mutex = Arc::new(Mutex::new(mock_transport)) as Arc<Mutex<dyn Transport>>;
-        let client = Client::new(transport_mutex);
+        let _client = Client::new(transport_mutex);
 
         // We can't directly test private fields, so we'll just ensure it compiles
     }
@@ -81,6 +135,34 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_send_user_message_with_tool_result() {
+        let mut mock_transport = MockTransport::new();
+        mock_transport
+            .expect_send()
+            .with(eq(json!({
+                "type": "user",
+                "message": {
+                    "role": "user",
+                    "content": [
+                        {"type": "tool_result", "tool_use_id": "toolu_1", "content": "done", "is_error": true}
+                    ]
+                },
+                "parent_tool_use_id": null,
+                "session_id": "default"
+            })))
+            .times(1)
+            .returning(|_| Ok(json!(null)));
+
+        let transport_mutex = Arc::new(Mutex::new(mock_transport)) as Arc<Mutex<dyn Transport>>;
+        let client = Client::new(transport_mutex);
+
+        let result = client
+            .send_user_message(UserMessage::tool_result("toolu_1", "done", true))
+            .await;
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn test_send_assistant_message() {
         let mut mock_transport = MockTransport::new();
@@ -130,6 +212,7 @@ mod tests {
             session_id: "session_123".to_string(),
             total_cost_usd: None,
             usage: None,
+            model_usage: None,
             result: None,
         };
 
@@ -169,4 +252,448 @@ mod tests {
         let result = client.send_stream_event(stream_event).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_query_streams_past_unknown_messages() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static SEEN: AtomicUsize = AtomicUsize::new(0);
+
+        let frames = std::sync::Mutex::new(vec![
+            json!({"type": "rate_limit", "retry_after_ms": 500}),
+            json!({
+                "type": "result", "subtype": "success", "duration_ms": 1, "duration_api_ms": 1,
+                "is_error": false, "num_turns": 1, "session_id": "s1"
+            }),
+            json!({"response": "done"}),
+        ]);
+        let mut mock_transport = MockTransport::new();
+        mock_transport.expect_send().returning(|_| Ok(json!(null)));
+        mock_transport
+            .expect_receive()
+            .returning(move || Ok(frames.lock().unwrap().remove(0)));
+        let transport_mutex = Arc::new(Mutex::new(mock_transport)) as Arc<Mutex<dyn Transport>>;
+        let client = Client::new(transport_mutex);
+
+        fn on_message(_message: Message) {
+            SEEN.fetch_add(1, Ordering::SeqCst);
+        }
+        let response = client
+            .query(vec![], None, None, Some(true), Some(on_message), None)
+            .await
+            .unwrap();
+
+        // The unknown message went to the callback instead of ending the query
+        assert_eq!(SEEN.load(Ordering::SeqCst), 2);
+        assert_eq!(response, json!({"response": "done"}));
+    }
+
+    #[tokio::test]
+    async fn test_receive_response_ends_after_result() {
+        let client = replaying_client(vec![
+            assistant_frame("one"),
+            assistant_frame("two"),
+            result_frame(),
+            assistant_frame("next turn"),
+        ]);
+
+        let messages: Vec<Message> = client
+            .receive_response()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[2], Message::Result(_)));
+
+        // The next response is still waiting on the transport
+        let next = client.get_next_message().await.unwrap();
+        assert!(matches!(next, Message::Assistant(_)));
+    }
+
+    #[tokio::test]
+    async fn test_receive_messages_with_combinators() {
+        let client = replaying_client(vec![
+            assistant_frame("hello"),
+            json!({"type": "system", "subtype": "init"}),
+            assistant_frame("world"),
+            result_frame(),
+        ]);
+
+        let texts: Vec<String> = client
+            .receive_messages()
+            .filter_map(|message| async move {
+                match message {
+                    Ok(Message::Assistant(assistant)) => match &assistant.content[0] {
+                        ContentBlock::Text(text) => Some(text.text.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+        assert_eq!(texts, ["hello", "world"]);
+    }
+
+    #[tokio::test]
+    async fn test_receive_messages_ends_after_error() {
+        let client = replaying_client(vec![assistant_frame("only")]);
+        let results: Vec<Result<Message, _>> = client.receive_messages().collect().await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(AgentError::TransportError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_dropping_stream_releases_transport() {
+        let client =
+            Client::new(Arc::new(Mutex::new(SilentTransport)) as Arc<Mutex<dyn Transport>>);
+        let mut stream = Box::pin(client.receive_messages());
+        let pending =
+            tokio::time::timeout(std::time::Duration::from_millis(20), stream.next()).await;
+        assert!(pending.is_err());
+        drop(stream);
+
+        // The read held the transport lock; sending only works once it is gone
+        let send = tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            client.send_message(json!({"type": "interrupt"})),
+        )
+        .await;
+        assert!(matches!(send, Ok(Ok(()))));
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_connect_sends_initialize_then_prompt() {
+        let cli = FakeCliTransport::new(false);
+        let mut client = ClaudeSDKClient::new_with_transport(SharedFakeCli(cli.clone()));
+        assert!(!client.is_connected());
+
+        client
+            .connect(Some("Help me analyze this codebase"))
+            .await
+            .unwrap();
+        assert!(client.is_connected());
+
+        let sent = cli.sent();
+        assert_eq!(sent[0]["request"]["subtype"], "initialize");
+        assert_eq!(
+            sent[1],
+            json!({
+                "type": "user",
+                "message": {"role": "user", "content": "Help me analyze this codebase"},
+                "parent_tool_use_id": null,
+                "session_id": "default"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_multi_turn_responses() {
+        let cli = FakeCliTransport::new(false);
+        let mut client = ClaudeSDKClient::new_with_transport(SharedFakeCli(cli.clone()));
+        client.connect(None).await.unwrap();
+
+        for (turn, text) in ["first", "second"].into_iter().enumerate() {
+            client.query(text).await.unwrap();
+            cli.push_frame(assistant_frame(text));
+            cli.push_frame(json!({"type": "system", "subtype": "status"}));
+            cli.push_frame(result_frame());
+
+            let messages: Vec<Message> = client
+                .receive_response()
+                .map(Result::unwrap)
+                .collect()
+                .await;
+            assert_eq!(messages.len(), 3, "turn {}", turn);
+            assert!(matches!(messages[2], Message::Result(_)));
+        }
+        let prompts: Vec<Value> = cli
+            .sent()
+            .into_iter()
+            .filter(|frame| frame["type"] == "user")
+            .map(|frame| frame["message"]["content"].clone())
+            .collect();
+        assert_eq!(prompts, [json!("first"), json!("second")]);
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_forwards_control_requests() {
+        let cli = FakeCliTransport::new(false);
+        let mut client = ClaudeSDKClient::new_with_transport(SharedFakeCli(cli.clone()));
+        client.connect(None).await.unwrap();
+
+        client.interrupt().await.unwrap();
+        client.set_model(Some("claude-sonnet-4-5")).await.unwrap();
+        client
+            .set_permission_mode(PermissionMode::AcceptEdits)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cli.control_subtypes(),
+            [
+                "initialize",
+                "interrupt",
+                "set_model",
+                "set_permission_mode"
+            ]
+        );
+        let sent = cli.sent();
+        assert_eq!(sent[2]["request"]["model"], "claude-sonnet-4-5");
+        assert_eq!(sent[3]["request"]["mode"], "acceptEdits");
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_answers_hooks_and_permissions_from_options() {
+        use claude_agent_sdk::hooks::{HookEvent, HookOutput};
+        use claude_agent_sdk::internal::query::{
+            HookMatcher, PermissionResult, PermissionResultDeny,
+        };
+
+        let cli = FakeCliTransport::new(false);
+        let options = ClaudeAgentOptions::new()
+            .hook(
+                HookEvent::PreToolUse,
+                HookMatcher::new(Some("Bash"))
+                    .hook(|_, _, _| async { Ok(HookOutput::new().block("no shell")) }),
+            )
+            .can_use_tool(|tool_name, _, _| async move {
+                Ok(PermissionResult::Deny(PermissionResultDeny {
+                    message: format!("{} is not allowed", tool_name),
+                    interrupt: false,
+                }))
+            });
+        let mut client =
+            ClaudeSDKClient::with_options_and_transport(options, SharedFakeCli(cli.clone()));
+        client.connect(None).await.unwrap();
+
+        let initialize = cli.sent()[0].clone();
+        let callback_id = initialize["request"]["hooks"]["PreToolUse"][0]["hookCallbackIds"][0]
+            .as_str()
+            .unwrap()
+            .to_string();
+        cli.push_frame(json!({
+            "type": "control_request",
+            "request_id": "cli_1",
+            "request": {"subtype": "can_use_tool", "tool_name": "Write", "input": {}}
+        }));
+        cli.push_frame(json!({
+            "type": "control_request",
+            "request_id": "cli_2",
+            "request": {
+                "subtype": "hook_callback",
+                "callback_id": callback_id,
+                "input": {
+                    "hook_event_name": "PreToolUse",
+                    "session_id": "s1",
+                    "transcript_path": "/tmp/t.jsonl",
+                    "cwd": "/tmp",
+                    "tool_name": "Bash",
+                    "tool_input": {}
+                }
+            }
+        }));
+
+        let mut answers = HashMap::new();
+        for _ in 0..100 {
+            for frame in cli.sent() {
+                if frame["type"] == "control_response" {
+                    answers.insert(
+                        frame["response"]["request_id"]
+                            .as_str()
+                            .unwrap()
+                            .to_string(),
+                        frame["response"]["response"].clone(),
+                    );
+                }
+            }
+            if answers.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(answers["cli_1"]["behavior"], "deny");
+        assert_eq!(answers["cli_1"]["message"], "Write is not allowed");
+        assert_eq!(answers["cli_2"]["decision"], "block");
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_requires_connection() {
+        let client = ClaudeSDKClient::new();
+        assert!(matches!(
+            client.query("hi").await,
+            Err(AgentError::NotConnected)
+        ));
+        assert!(matches!(
+            client.interrupt().await,
+            Err(AgentError::NotConnected)
+        ));
+        let messages: Vec<_> = client.receive_messages().collect().await;
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(messages[0], Err(AgentError::NotConnected)));
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_disconnect_closes_transport() {
+        let cli = FakeCliTransport::new(false);
+        let mut client = ClaudeSDKClient::new_with_transport(SharedFakeCli(cli.clone()));
+        client.connect(None).await.unwrap();
+
+        client.disconnect().await.unwrap();
+        assert!(cli.closed.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(!client.is_connected());
+        assert!(matches!(
+            client.query("hi").await,
+            Err(AgentError::NotConnected)
+        ));
+        // Disconnecting twice is fine
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_connect_with_stream_keeps_input_open() {
+        let cli = FakeCliTransport::new(false);
+        let mut client = ClaudeSDKClient::new_with_transport(SharedFakeCli(cli.clone()));
+        let (prompts, messages) = futures::channel::mpsc::unbounded();
+        client.connect_with_stream(messages).await.unwrap();
+
+        prompts.unbounded_send(UserMessage::text("first")).unwrap();
+        cli.push_frame(result_frame());
+        let response: Vec<Message> = client
+            .receive_response()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(response.len(), 1);
+        assert_eq!(cli.sent()[1]["message"]["content"], "first");
+        assert!(!cli.input_ended.load(std::sync::atomic::Ordering::SeqCst));
+
+        prompts.unbounded_send(UserMessage::text("second")).unwrap();
+        drop(prompts);
+        for _ in 0..50 {
+            if cli.input_ended.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(cli.input_ended.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(cli.sent()[2]["message"]["content"], "second");
+        // Control requests still work after the input has ended
+        client.interrupt().await.unwrap();
+        assert_eq!(cli.control_subtypes(), ["initialize", "interrupt"]);
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_stream_keeps_input_open_for_callbacks() {
+        use claude_agent_sdk::internal::query::{PermissionResult, PermissionResultAllow};
+
+        let cli = FakeCliTransport::new(false);
+        let options = ClaudeAgentOptions::new().can_use_tool(|_, _, _| async {
+            Ok(PermissionResult::Allow(PermissionResultAllow::default()))
+        });
+        let mut client =
+            ClaudeSDKClient::with_options_and_transport(options, SharedFakeCli(cli.clone()));
+        client
+            .connect_with_stream(futures::stream::iter([UserMessage::text("hi")]))
+            .await
+            .unwrap();
+
+        // The stream has ended, but the CLI may still ask for permissions
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(cli.sent()[1]["message"]["content"], "hi");
+        assert!(!cli.input_ended.load(std::sync::atomic::Ordering::SeqCst));
+
+        cli.push_frame(result_frame());
+        for _ in 0..50 {
+            if cli.input_ended.load(std::sync::atomic::Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("input was not closed after the result");
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_stream_keeps_input_open_until_every_turn_ends() {
+        use claude_agent_sdk::hooks::{HookEvent, HookOutput};
+        use claude_agent_sdk::internal::query::HookMatcher;
+
+        let cli = FakeCliTransport::new(false);
+        let options = ClaudeAgentOptions::new().hook(
+            HookEvent::PreToolUse,
+            HookMatcher::new(None).hook(|_, _, _| async { Ok(HookOutput::new()) }),
+        );
+        let mut client =
+            ClaudeSDKClient::with_options_and_transport(options, SharedFakeCli(cli.clone()));
+        client
+            .connect_with_stream(futures::stream::iter([
+                UserMessage::text("first"),
+                UserMessage::text("second"),
+            ]))
+            .await
+            .unwrap();
+        let callback_id = cli.sent()[0]["request"]["hooks"]["PreToolUse"][0]["hookCallbackIds"][0]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        cli.push_frame(assistant_frame("one"));
+        cli.push_frame(result_frame());
+        let first: Vec<_> = Box::pin(client.receive_response()).collect().await;
+        assert!(matches!(first.last(), Some(Ok(Message::Result(_)))));
+        assert!(!cli.input_ended.load(std::sync::atomic::Ordering::SeqCst));
+
+        // The second turn still calls back into the SDK over stdin
+        cli.push_frame(json!({
+            "type": "control_request",
+            "request_id": "cli_1",
+            "request": {
+                "subtype": "hook_callback",
+                "callback_id": callback_id,
+                "input": {
+                    "hook_event_name": "PreToolUse",
+                    "session_id": "s1",
+                    "transcript_path": "/tmp/t.jsonl",
+                    "cwd": "/tmp",
+                    "tool_name": "Bash",
+                    "tool_input": {}
+                }
+            }
+        }));
+        cli.push_frame(assistant_frame("two"));
+        cli.push_frame(result_frame());
+        let second: Vec<_> = Box::pin(client.receive_response()).collect().await;
+        assert!(matches!(second.last(), Some(Ok(Message::Result(_)))));
+        assert!(cli
+            .sent()
+            .iter()
+            .any(|frame| frame["type"] == "control_response"
+                && frame["response"]["request_id"] == "cli_1"));
+
+        for _ in 0..50 {
+            if cli.input_ended.load(std::sync::atomic::Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("input was not closed after both results");
+    }
+
+    #[tokio::test]
+    async fn test_sdk_client_drop_releases_transport() {
+        let cli = FakeCliTransport::new(false);
+        let transport = SharedFakeCli(cli.clone());
+        let mut client = ClaudeSDKClient::new_with_transport(transport);
+        client.connect(None).await.unwrap();
+        drop(client);
+
+        // The reader task is aborted, so nothing is left holding the fake CLI
+        for _ in 0..50 {
+            if Arc::strong_count(&cli) == 1 {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("transport still alive after the client was dropped");
+    }
 }
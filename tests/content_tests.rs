@@ -0,0 +1,140 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::types::*;
+use serde_json::json;
+use std::path::PathBuf;
+
+// Writes `bytes` to a fresh temp file with the given file name
+fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("content-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    #[test]
+    fn test_image_from_path_sniffs_media_type() {
+        // The contents win over a misleading extension
+        let path = temp_file("screenshot.jpg", PNG_HEADER);
+        let block = ContentBlock::image_from_path(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}
+            })
+        );
+    }
+
+    #[test]
+    fn test_image_from_path_rejects_other_files() {
+        let path = temp_file("report.pdf", b"%PDF-1.7");
+        match ContentBlock::image_from_path(&path) {
+            Err(AgentError::InvalidAttachment { path: p, reason }) => {
+                assert_eq!(p, path);
+                assert_eq!(reason, "not a PNG, JPEG, GIF or WebP image");
+            }
+            other => panic!("Expected invalid attachment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_image_from_path_enforces_size_limit() {
+        let mut bytes = PNG_HEADER.to_vec();
+        bytes.resize(MAX_IMAGE_BYTES as usize + 1, 0);
+        let path = temp_file("huge.png", &bytes);
+        let err = ContentBlock::image_from_path(&path).unwrap_err();
+        assert!(err.to_string().contains("exceeds the 5242880 byte limit"));
+    }
+
+    #[test]
+    fn test_image_from_missing_path_is_io_error() {
+        let err = ContentBlock::image_from_path("/nonexistent/shot.png").unwrap_err();
+        assert!(matches!(err, AgentError::IOError(_)));
+    }
+
+    #[test]
+    fn test_document_from_path() {
+        let pdf = ContentBlock::document_from_path(temp_file("spec.pdf", b"%PDF-1.7")).unwrap();
+        assert_eq!(
+            serde_json::to_value(&pdf).unwrap(),
+            json!({
+                "type": "document",
+                "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0xLjc="},
+                "title": "spec.pdf"
+            })
+        );
+
+        let text = ContentBlock::document_from_path(temp_file("notes.md", b"# Notes")).unwrap();
+        assert_eq!(
+            serde_json::to_value(&text).unwrap(),
+            json!({
+                "type": "document",
+                "source": {"type": "text", "media_type": "text/plain", "data": "# Notes"},
+                "title": "notes.md"
+            })
+        );
+
+        let err = ContentBlock::document_from_path(temp_file("data.bin", &[0, 1, 2])).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("not a PDF or plain-text document"));
+    }
+
+    #[test]
+    fn test_url_sources_round_trip() {
+        let block = ContentBlock::image_url("https://example.com/cat.png");
+        let value = serde_json::to_value(&block).unwrap();
+        assert_eq!(
+            value,
+            json!({"type": "image", "source": {"type": "url", "url": "https://example.com/cat.png"}})
+        );
+        match serde_json::from_value(value).unwrap() {
+            ContentBlock::Image(image) => assert_eq!(
+                image.source,
+                ContentSource::Url {
+                    url: "https://example.com/cat.png".to_string()
+                }
+            ),
+            other => panic!("Expected image block, got {:?}", other),
+        }
+
+        let document =
+            serde_json::to_value(ContentBlock::document_url("https://example.com/a.pdf"));
+        assert_eq!(
+            document.unwrap(),
+            json!({"type": "document", "source": {"type": "url", "url": "https://example.com/a.pdf"}})
+        );
+    }
+
+    #[test]
+    fn test_user_input_frame_with_image() {
+        let message = UserMessage::with_blocks(vec![
+            ContentBlock::Text(TextBlock {
+                text: "What is wrong here?".to_string(),
+            }),
+            ContentBlock::image_url("https://example.com/error.png"),
+        ]);
+        assert_eq!(
+            message.to_input_frame("s1"),
+            json!({
+                "type": "user",
+                "message": {
+                    "role": "user",
+                    "content": [
+                        {"type": "text", "text": "What is wrong here?"},
+                        {"type": "image", "source": {"type": "url", "url": "https://example.com/error.png"}}
+                    ]
+                },
+                "parent_tool_use_id": null,
+                "session_id": "s1"
+            })
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use claude_agent_sdk::control::*;
+use claude_agent_sdk::types::PermissionMode;
+use serde_json::{json, Value};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupt_request_serialization() {
+        let frame = SDKControlRequest {
+            request_id: "req_1".to_string(),
+            request: ControlRequest::Interrupt,
+        };
+        assert_eq!(
+            serde_json::to_value(&frame).unwrap(),
+            json!({
+                "type": "control_request",
+                "request_id": "req_1",
+                "request": {"subtype": "interrupt"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_initialize_request_serialization() {
+        let request = ControlRequest::Initialize { hooks: None };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"subtype": "initialize"})
+        );
+
+        let mut hooks = std::collections::HashMap::new();
+        hooks.insert(
+            "PreToolUse".to_string(),
+            vec![HookMatcherConfig {
+                matcher: Some("Bash".to_string()),
+                hook_callback_ids: vec!["hook_0".to_string()],
+                timeout: None,
+            }],
+        );
+        let request = ControlRequest::Initialize { hooks: Some(hooks) };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "subtype": "initialize",
+                "hooks": {
+                    "PreToolUse": [{"matcher": "Bash", "hookCallbackIds": ["hook_0"]}]
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_permission_mode_and_model_serialization() {
+        let request = ControlRequest::SetPermissionMode {
+            mode: PermissionMode::BypassPermissions,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"subtype": "set_permission_mode", "mode": "bypassPermissions"})
+        );
+
+        let request = ControlRequest::SetModel { model: None };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"subtype": "set_model", "model": null})
+        );
+    }
+
+    #[test]
+    fn test_inbound_requests_deserialize() {
+        let frame: SDKControlRequest = serde_json::from_value(json!({
+            "type": "control_request",
+            "request_id": "cli_1",
+            "request": {
+                "subtype": "can_use_tool",
+                "tool_name": "Bash",
+                "input": {"command": "ls"},
+                "permission_suggestions": null,
+            },
+        }))
+        .unwrap();
+        assert_eq!(frame.request_id, "cli_1");
+        match frame.request {
+            ControlRequest::CanUseTool {
+                tool_name,
+                input,
+                blocked_path,
+                ..
+            } => {
+                assert_eq!(tool_name, "Bash");
+                assert_eq!(input["command"], "ls");
+                assert_eq!(blocked_path, None);
+            }
+            other => panic!("unexpected request: {:?}", other),
+        }
+
+        let request: ControlRequest = serde_json::from_value(json!({
+            "subtype": "hook_callback",
+            "callback_id": "hook_0",
+            "input": {"hook_event_name": "PreToolUse"},
+        }))
+        .unwrap();
+        assert_eq!(request.subtype(), "hook_callback");
+
+        let request: ControlRequest = serde_json::from_value(json!({
+            "subtype": "mcp_message",
+            "server_name": "calc",
+            "message": {"jsonrpc": "2.0", "id": 1, "method": "tools/list"},
+        }))
+        .unwrap();
+        assert_eq!(request.subtype(), "mcp_message");
+    }
+
+    #[test]
+    fn test_unknown_subtype_is_rejected() {
+        let result = serde_json::from_value::<ControlRequest>(json!({"subtype": "reboot"}));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_response_envelopes() {
+        let success: SDKControlResponse = serde_json::from_value(json!({
+            "type": "control_response",
+            "response": {"subtype": "success", "request_id": "req_1", "response": {"ok": true}},
+        }))
+        .unwrap();
+        assert_eq!(success.response.request_id(), "req_1");
+        assert_eq!(success.response.into_result(), Ok(json!({"ok": true})));
+
+        let error = SDKControlResponse {
+            response: ControlResponse::error("req_2", "boom"),
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "type": "control_response",
+                "response": {"subtype": "error", "request_id": "req_2", "error": "boom"},
+            })
+        );
+        assert_eq!(error.response.into_result(), Err("boom".to_string()));
+
+        let empty = ControlResponse::success("req_3", None);
+        assert_eq!(empty.into_result(), Ok(Value::Null));
+    }
+
+    #[test]
+    fn test_control_message_dispatches_on_type() {
+        let message: ControlMessage = serde_json::from_value(json!({
+            "type": "control_response",
+            "response": {"subtype": "success", "request_id": "req_1"},
+        }))
+        .unwrap();
+        assert!(matches!(message, ControlMessage::ControlResponse { .. }));
+
+        let message: ControlMessage = SDKControlRequest {
+            request_id: "req_4".to_string(),
+            request: ControlRequest::Interrupt,
+        }
+        .into();
+        assert_eq!(
+            serde_json::to_value(&message).unwrap()["type"],
+            "control_request"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use claude_agent_sdk::error::AgentError;
-use serde_json::{Error, Value};
+use claude_agent_sdk::message_parser::MessageParseError;
+use serde_json::Value;
 use std::io;
 
 #[cfg(test)]
@@ -24,7 +25,7 @@ mod tests {
 
     #[test]
     fn test_io_error() {
-        let io_error = io::Error::new(io::ErrorKind::Other, "test error");
+        let io_error = io::Error::other("test error");
         let agent_error = AgentError::IOError(io_error);
         let msg = format!("{}", agent_error);
         assert!(msg.contains("IO error"));
@@ -39,6 +40,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_message_parsing_error() {
         let agent_error = AgentError::MessageParsingError("test error".to_string());
         let msg = format!("{}", agent_error);
@@ -46,6 +48,17 @@ mod tests {
         assert!(msg.contains("test error"));
     }
 
+    #[test]
+    fn test_message_parse_error() {
+        let agent_error = AgentError::MessageParse(MessageParseError::new(
+            "test error",
+            &serde_json::json!({"type": "assistant"}),
+        ));
+        let msg = format!("{}", agent_error);
+        assert!(msg.contains("Message parse error"));
+        assert!(msg.contains("test error"));
+    }
+
     #[test]
     fn test_invalid_message_type_error() {
         let agent_error = AgentError::InvalidMessageType("test error".to_string());
@@ -96,7 +109,7 @@ mod tests {
 
     #[test]
     fn test_error_from_io() {
-        let io_error = io::Error::new(io::ErrorKind::Other, "test io error");
+        let io_error = io::Error::other("test io error");
         let agent_error = AgentError::from(io_error);
         let msg = format!("{}", agent_error);
         assert!(msg.contains("IO error"));
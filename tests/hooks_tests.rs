@@ -0,0 +1,160 @@
+use claude_agent_sdk::hooks::*;
+use serde_json::json;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_event_wire_names() {
+        assert_eq!(
+            serde_json::to_value(HookEvent::PreToolUse).unwrap(),
+            json!("PreToolUse")
+        );
+        assert_eq!(HookEvent::SubagentStop.as_str(), "SubagentStop");
+        assert_eq!(HookEvent::SessionEnd.to_string(), "SessionEnd");
+    }
+
+    #[test]
+    fn test_pre_tool_use_input_deserialization() {
+        let input: HookInput = serde_json::from_value(json!({
+            "hook_event_name": "PreToolUse",
+            "session_id": "session_1",
+            "transcript_path": "/tmp/transcript.jsonl",
+            "cwd": "/work",
+            "permission_mode": "default",
+            "tool_name": "Bash",
+            "tool_input": {"command": "ls"},
+        }))
+        .unwrap();
+
+        assert_eq!(input.event(), HookEvent::PreToolUse);
+        assert_eq!(input.tool_name(), Some("Bash"));
+        assert_eq!(input.tool_input().unwrap()["command"], "ls");
+        assert_eq!(input.base().cwd, "/work");
+        assert_eq!(input.base().permission_mode.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_post_tool_use_input_deserialization() {
+        let input: HookInput = serde_json::from_value(json!({
+            "hook_event_name": "PostToolUse",
+            "session_id": "session_1",
+            "transcript_path": "/tmp/transcript.jsonl",
+            "cwd": "/work",
+            "tool_name": "Read",
+            "tool_input": {"file_path": "a.txt"},
+            "tool_response": {"content": "hello"},
+        }))
+        .unwrap();
+
+        match input {
+            HookInput::PostToolUse(input) => {
+                assert_eq!(input.tool_response["content"], "hello");
+                assert_eq!(input.base.permission_mode, None);
+            }
+            other => panic!("unexpected input: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_other_event_inputs_deserialization() {
+        let base = json!({
+            "session_id": "s",
+            "transcript_path": "t",
+            "cwd": "c",
+        });
+        let with = |extra: serde_json::Value| {
+            let mut value = base.clone();
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            serde_json::from_value::<HookInput>(value).unwrap()
+        };
+
+        let input = with(json!({"hook_event_name": "UserPromptSubmit", "prompt": "hi"}));
+        assert!(matches!(input, HookInput::UserPromptSubmit(ref i) if i.prompt == "hi"));
+
+        let input = with(json!({"hook_event_name": "SubagentStop", "stop_hook_active": true}));
+        assert_eq!(input.event(), HookEvent::SubagentStop);
+
+        let input = with(json!({"hook_event_name": "PreCompact", "trigger": "auto"}));
+        assert!(matches!(input, HookInput::PreCompact(ref i) if i.custom_instructions.is_none()));
+
+        let input = with(json!({"hook_event_name": "Notification", "message": "idle"}));
+        assert_eq!(input.event(), HookEvent::Notification);
+
+        let input = with(json!({"hook_event_name": "SessionStart", "source": "resume"}));
+        assert_eq!(input.tool_name(), None);
+
+        let input = with(json!({"hook_event_name": "SessionEnd", "reason": "logout"}));
+        assert_eq!(input.event(), HookEvent::SessionEnd);
+    }
+
+    #[test]
+    fn test_default_output_is_empty_object() {
+        assert_eq!(serde_json::to_value(HookOutput::new()).unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_output_builder_serializes_camel_case() {
+        let output = HookOutput::new()
+            .stop("Build is broken")
+            .suppress_output(true)
+            .system_message("Stopping early");
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({
+                "continue": false,
+                "stopReason": "Build is broken",
+                "suppressOutput": true,
+                "systemMessage": "Stopping early",
+            })
+        );
+
+        let output = HookOutput::new().block("Tests must pass first");
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({"decision": "block", "reason": "Tests must pass first"})
+        );
+    }
+
+    #[test]
+    fn test_hook_specific_output() {
+        let output =
+            HookOutput::new().permission_decision(PermissionDecision::Ask, "Touches production");
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "PreToolUse",
+                    "permissionDecision": "ask",
+                    "permissionDecisionReason": "Touches production",
+                }
+            })
+        );
+
+        let output =
+            HookOutput::new().additional_context(HookEvent::SessionStart, "Use the staging DB");
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "SessionStart",
+                    "additionalContext": "Use the staging DB",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_output_round_trip() {
+        let output = HookOutput::new()
+            .permission_decision(PermissionDecision::Deny, "no")
+            .system_message("denied");
+        let value = serde_json::to_value(&output).unwrap();
+        let parsed: HookOutput = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, output);
+    }
+}
@@ -0,0 +1,358 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::mcp::jsonrpc::METHOD_NOT_FOUND;
+use claude_agent_sdk::mcp::*;
+use serde_json::{json, Value};
+use std::io::Write;
+#[cfg(feature = "mcp-http")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "mcp-http")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "mcp-http")]
+use tokio::net::{TcpListener, TcpStream};
+
+// Answers by method, echoing the request id; notifications get no reply
+const STDIO_STUB: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/^{"jsonrpc":"2.0","id":\([0-9]*\),.*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      echo 'stub server starting'
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"stub","version":"0.1.0"}}}\n' "$id" ;;
+    *'"method":"tools/list"'*)
+      printf '{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}\n'
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echoes text","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+    *'"method":"tools/call"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echoed"}]}}\n' "$id" ;;
+    *'"method":"resources/list"'*'"cursor"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"resources":[{"uri":"file:///b.txt","name":"b"}]}}\n' "$id" ;;
+    *'"method":"resources/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"resources":[{"uri":"file:///a.txt","name":"a","mimeType":"text/plain"}],"nextCursor":"page-2"}}\n' "$id" ;;
+    *'"method":"resources/read"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"contents":[{"uri":"file:///a.txt","mimeType":"text/plain","text":"hello"}]}}\n' "$id" ;;
+    *'"method":"prompts/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"prompts":[{"name":"review","arguments":[{"name":"code","required":true}]}]}}\n' "$id" ;;
+    *'"id":'*)
+      printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"Method not found"}}\n' "$id" ;;
+  esac
+done
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stdio_stub() -> McpStdioServerConfig {
+        stdio_stub_script(STDIO_STUB)
+    }
+
+    fn stdio_stub_script(script: &str) -> McpStdioServerConfig {
+        let path = std::env::temp_dir().join(format!("mcp-stub-{}.sh", uuid::Uuid::new_v4()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(script.as_bytes()).unwrap();
+        McpStdioServerConfig::new("sh").args([path.to_string_lossy()])
+    }
+
+    #[tokio::test]
+    async fn test_stdio_initialize_handshake() {
+        let client = Client::connect_stdio(&stdio_stub()).await.unwrap();
+        assert_eq!(client.server_info()["serverInfo"]["name"], "stub");
+        assert_eq!(
+            client.server_info()["protocolVersion"],
+            MCP_PROTOCOL_VERSION
+        );
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stdio_list_and_call_tools() {
+        let client = Client::connect_stdio(&stdio_stub()).await.unwrap();
+
+        // The stub sends a notification before the response, which must be skipped
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "echo");
+        assert_eq!(tools[0].description.as_deref(), Some("Echoes text"));
+        assert_eq!(tools[0].input_schema, json!({"type": "object"}));
+
+        let result = client
+            .call_tool("echo", json!({"text": "hi"}))
+            .await
+            .unwrap();
+        assert_eq!(result, ToolResult::text("echoed"));
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stdio_resources_follow_cursor() {
+        let client = Client::connect_stdio(&stdio_stub()).await.unwrap();
+
+        let resources = client.list_resources().await.unwrap();
+        let uris: Vec<&str> = resources.iter().map(|r| r.uri.as_str()).collect();
+        assert_eq!(uris, ["file:///a.txt", "file:///b.txt"]);
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/plain"));
+
+        let contents = client.read_resource("file:///a.txt").await.unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0].text.as_deref(), Some("hello"));
+        assert_eq!(contents[0].blob, None);
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stdio_list_prompts() {
+        let client = Client::connect_stdio(&stdio_stub()).await.unwrap();
+        let prompts = client.list_prompts().await.unwrap();
+        assert_eq!(
+            prompts,
+            vec![McpPrompt {
+                name: "review".to_string(),
+                description: None,
+                arguments: vec![McpPromptArgument {
+                    name: "code".to_string(),
+                    description: None,
+                    required: true,
+                }],
+            }]
+        );
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stdio_malformed_list_is_an_error() {
+        // A tool without a name is not a valid `tools/list` page
+        let script = STDIO_STUB.replace(r#"{"tools":[{"name":"echo","#, r#"{"tools":[{"#);
+        let client = Client::connect_stdio(&stdio_stub_script(&script))
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.list_tools().await,
+            Err(AgentError::SerializationError(_))
+        ));
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stdio_error_response() {
+        let client = Client::connect_stdio(&stdio_stub()).await.unwrap();
+        match client.request("completion/complete", None).await {
+            Err(AgentError::McpError { code, message }) => {
+                assert_eq!(code, METHOD_NOT_FOUND);
+                assert_eq!(message, "Method not found");
+            }
+            other => panic!("Expected McpError, got {:?}", other),
+        }
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stdio_server_that_exits() {
+        let config = McpStdioServerConfig::new("sh").args(["-c", "exit 0"]);
+        assert!(Client::connect_stdio(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sdk_server_client() {
+        let server = SdkMcpServer::new("calc").tool(SdkMcpTool::new(
+            "add",
+            "Adds two numbers",
+            json!({"type": "object"}),
+            |args: Value| async move {
+                let sum = args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0);
+                Ok(ToolResult::text(sum.to_string()))
+            },
+        ));
+        let client = Client::connect(&McpServerConfig::sdk(server))
+            .await
+            .unwrap();
+        assert_eq!(client.server_info()["serverInfo"]["name"], "calc");
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "add");
+        let result = client
+            .call_tool("add", json!({"a": 2, "b": 3}))
+            .await
+            .unwrap();
+        assert_eq!(result, ToolResult::text("5"));
+
+        // The in-process server only serves tools
+        assert!(matches!(
+            client.list_resources().await,
+            Err(AgentError::McpError {
+                code: METHOD_NOT_FOUND,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejects_sse() {
+        let config = McpServerConfig::sse("http://localhost:1/sse");
+        assert!(Client::connect(&config).await.is_err());
+    }
+
+    #[cfg(feature = "mcp-http")]
+    // Requests seen by the HTTP stub: method and the session header it carried
+    type SeenRequests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+    #[cfg(feature = "mcp-http")]
+    async fn read_http_request(stream: &mut TcpStream) -> Option<(String, Vec<String>, Vec<u8>)> {
+        let mut buf = Vec::new();
+        let header_end = loop {
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            let mut chunk = [0u8; 1024];
+            let n = stream.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let method = lines.next()?.split(' ').next()?.to_string();
+        let headers: Vec<String> = lines
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_lowercase())
+            .collect();
+        let length = headers
+            .iter()
+            .find_map(|h| h.strip_prefix("content-length:"))
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        let mut body = buf[header_end..].to_vec();
+        while body.len() < length {
+            let mut chunk = [0u8; 1024];
+            let n = stream.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            body.extend_from_slice(&chunk[..n]);
+        }
+        Some((method, headers, body))
+    }
+
+    #[cfg(feature = "mcp-http")]
+    fn http_reply(request: &Value) -> (&'static str, String) {
+        let id = request["id"].clone();
+        match request["method"].as_str().unwrap_or("") {
+            "initialize" => (
+                "application/json",
+                json!({"jsonrpc": "2.0", "id": id, "result": {
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "http-stub", "version": "0.1.0"},
+                }})
+                .to_string(),
+            ),
+            "tools/list" => (
+                "application/json",
+                json!({"jsonrpc": "2.0", "id": id, "result": {
+                    "tools": [{"name": "search", "inputSchema": {"type": "object"}}],
+                }})
+                .to_string(),
+            ),
+            // Answer as an event stream with a progress notification first
+            _ => (
+                "text/event-stream",
+                format!(
+                    "event: message\ndata: {}\n\nevent: message\ndata: {}\n\n",
+                    json!({"jsonrpc": "2.0", "method": "notifications/progress", "params": {}}),
+                    json!({"jsonrpc": "2.0", "id": id, "result": {
+                        "content": [{"type": "text", "text": "found it"}],
+                    }})
+                ),
+            ),
+        }
+    }
+
+    #[cfg(feature = "mcp-http")]
+    async fn serve_http_connection(mut stream: TcpStream, seen: SeenRequests) {
+        while let Some((method, headers, body)) = read_http_request(&mut stream).await {
+            let session = headers
+                .iter()
+                .find_map(|h| h.strip_prefix("mcp-session-id:"))
+                .map(|v| v.trim().to_string());
+            let response = if method == "POST" {
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                seen.lock().unwrap().push((
+                    request["method"].as_str().unwrap_or("").to_string(),
+                    session,
+                ));
+                if request.get("id").is_none() {
+                    "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else {
+                    let (content_type, body) = http_reply(&request);
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nMcp-Session-Id: session-1\r\nContent-Length: {}\r\n\r\n{}",
+                        content_type,
+                        body.len(),
+                        body
+                    )
+                }
+            } else {
+                seen.lock().unwrap().push((method, session));
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_string()
+            };
+            if stream.write_all(response.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    #[cfg(feature = "mcp-http")]
+    async fn http_stub() -> (String, SeenRequests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let seen = SeenRequests::default();
+        let accepted = seen.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_http_connection(stream, accepted.clone()));
+            }
+        });
+        (url, seen)
+    }
+
+    #[cfg(feature = "mcp-http")]
+    #[tokio::test]
+    async fn test_http_client_session() {
+        let (url, seen) = http_stub().await;
+        let client = Client::connect_http(&McpRemoteServerConfig::new(url))
+            .await
+            .unwrap();
+        assert_eq!(client.server_info()["serverInfo"]["name"], "http-stub");
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "search");
+
+        let result = client.call_tool("search", json!({})).await.unwrap();
+        assert_eq!(result, ToolResult::text("found it"));
+        client.close().await.unwrap();
+
+        // Every request after initialize carries the session the server handed out
+        let seen = seen.lock().unwrap().clone();
+        let session = Some("session-1".to_string());
+        assert_eq!(
+            seen,
+            vec![
+                ("initialize".to_string(), None),
+                ("notifications/initialized".to_string(), session.clone()),
+                ("tools/list".to_string(), session.clone()),
+                ("tools/call".to_string(), session.clone()),
+                ("DELETE".to_string(), session),
+            ]
+        );
+    }
+
+    #[cfg(feature = "mcp-http")]
+    #[tokio::test]
+    async fn test_http_server_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(Client::connect_http(&McpRemoteServerConfig::new(url))
+            .await
+            .is_err());
+    }
+}
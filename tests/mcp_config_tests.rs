@@ -0,0 +1,176 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::mcp::*;
+use claude_agent_sdk::types::ClaudeAgentOptions;
+use serde_json::json;
+use std::io::Write;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp_file(contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("mcp-{}.json", uuid::Uuid::new_v4()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_stdio_config_serialization() {
+        let config: McpServerConfig = McpStdioServerConfig::new("npx")
+            .args(["-y", "@modelcontextprotocol/server-filesystem"])
+            .env("ROOT", "/tmp")
+            .into();
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "type": "stdio",
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-filesystem"],
+                "env": {"ROOT": "/tmp"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_remote_config_serialization() {
+        let config = McpServerConfig::Sse(
+            McpRemoteServerConfig::new("https://example.com/sse")
+                .header("Authorization", "Bearer x"),
+        );
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "type": "sse",
+                "url": "https://example.com/sse",
+                "headers": {"Authorization": "Bearer x"},
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(McpServerConfig::http("http://localhost:8080/mcp")).unwrap(),
+            json!({"type": "http", "url": "http://localhost:8080/mcp"})
+        );
+    }
+
+    #[test]
+    fn test_sdk_config_sends_only_the_name() {
+        let config = McpServerConfig::sdk(SdkMcpServer::new("calc"));
+        assert_eq!(config.server_type(), "sdk");
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({"type": "sdk", "name": "calc"})
+        );
+    }
+
+    #[test]
+    fn test_deserialize_defaults_to_stdio() {
+        let config: McpServerConfig =
+            serde_json::from_value(json!({"command": "server", "args": ["--port", "1"]})).unwrap();
+        match config {
+            McpServerConfig::Stdio(config) => {
+                assert_eq!(config.command, "server");
+                assert_eq!(config.args, vec!["--port", "1"]);
+            }
+            other => panic!("unexpected config: {:?}", other),
+        }
+
+        let config: McpServerConfig =
+            serde_json::from_value(json!({"type": "http", "url": "https://x.dev"})).unwrap();
+        assert_eq!(config.server_type(), "http");
+    }
+
+    #[test]
+    fn test_deserialize_rejects_sdk_and_unknown_types() {
+        assert!(
+            serde_json::from_value::<McpServerConfig>(json!({"type": "sdk", "name": "x"})).is_err()
+        );
+        assert!(
+            serde_json::from_value::<McpServerConfig>(json!({"type": "ws", "url": "x"})).is_err()
+        );
+        assert!(serde_json::from_value::<McpServerConfig>(json!("npx")).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(McpServerConfig::stdio("server").validate("ok").is_ok());
+        assert!(McpServerConfig::stdio("server").validate(" ").is_err());
+
+        let err = McpServerConfig::stdio("").validate("empty").unwrap_err();
+        assert!(matches!(
+            err,
+            AgentError::InvalidMcpServerConfig { ref name, .. } if name == "empty"
+        ));
+
+        let err = McpServerConfig::sse("example.com/sse")
+            .validate("remote")
+            .unwrap_err();
+        assert!(err.to_string().contains("http:// or https://"));
+
+        let config =
+            McpServerConfig::Http(McpRemoteServerConfig::new("https://x.dev").header("", "v"));
+        assert!(config.validate("headers").is_err());
+    }
+
+    #[test]
+    fn test_load_file() {
+        let path = write_temp_file(
+            r#"{
+                "mcpServers": {
+                    "files": {"command": "npx", "args": ["server-filesystem"]},
+                    "docs": {"type": "sse", "url": "https://docs.example.com/sse"}
+                }
+            }"#,
+        );
+        let servers = McpServerConfig::load_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers["files"].server_type(), "stdio");
+        assert_eq!(servers["docs"].server_type(), "sse");
+    }
+
+    #[test]
+    fn test_load_file_fails_fast_on_invalid_entry() {
+        let path =
+            write_temp_file(r#"{"mcpServers": {"broken": {"type": "http", "url": "ftp://x"}}}"#);
+        let result = McpServerConfig::load_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(AgentError::InvalidMcpServerConfig { ref name, .. }) if name == "broken"
+        ));
+
+        assert!(McpServerConfig::load_file("/nonexistent/.mcp.json").is_err());
+    }
+
+    #[test]
+    fn test_options_mcp_config_arg() {
+        let options = ClaudeAgentOptions::new()
+            .mcp_server("calc", SdkMcpServer::new("calc"))
+            .mcp_server("files", McpServerConfig::stdio("npx"));
+        let args = options.to_cli_args();
+        let index = args.iter().position(|arg| arg == "--mcp-config").unwrap();
+        let config: serde_json::Value = serde_json::from_str(&args[index + 1]).unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "mcpServers": {
+                    "calc": {"type": "sdk", "name": "calc"},
+                    "files": {"type": "stdio", "command": "npx"},
+                }
+            })
+        );
+
+        let sdk_servers = options.sdk_mcp_servers();
+        assert_eq!(sdk_servers.len(), 1);
+        assert_eq!(sdk_servers["calc"].name(), "calc");
+    }
+
+    #[test]
+    fn test_options_validate() {
+        let options =
+            ClaudeAgentOptions::new().mcp_server("bad", McpServerConfig::http("localhost"));
+        assert!(options.validate().is_err());
+        assert!(ClaudeAgentOptions::new().validate().is_ok());
+    }
+}
@@ -0,0 +1,170 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::mcp::*;
+use serde_json::{json, Value};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculator() -> SdkMcpServer {
+        SdkMcpServer::new("calc")
+            .version("2.0.0")
+            .tool(SdkMcpTool::new(
+                "add",
+                "Add two numbers",
+                json!({
+                    "type": "object",
+                    "properties": {"a": {"type": "number"}, "b": {"type": "number"}},
+                    "required": ["a", "b"],
+                }),
+                |args: Value| async move {
+                    let a = args["a"].as_f64().unwrap_or_default();
+                    let b = args["b"].as_f64().unwrap_or_default();
+                    Ok(ToolResult::text(format!("{}", a + b)))
+                },
+            ))
+            .tool(SdkMcpTool::new(
+                "divide",
+                "Divide two numbers",
+                json!({"type": "object"}),
+                |args: Value| async move {
+                    let b = args["b"].as_f64().unwrap_or_default();
+                    if b == 0.0 {
+                        return Err(AgentError::ToolExecutionError(
+                            "Division by zero".to_string(),
+                        ));
+                    }
+                    Ok(ToolResult::text(format!(
+                        "{}",
+                        args["a"].as_f64().unwrap_or_default() / b
+                    )))
+                },
+            ))
+    }
+
+    #[test]
+    fn test_server_config_is_sdk_type() {
+        let server = calculator();
+        assert_eq!(server.name(), "calc");
+        assert_eq!(server.tools().len(), 2);
+        assert_eq!(
+            serde_json::to_value(McpServerConfig::sdk(server)).unwrap(),
+            json!({"type": "sdk", "name": "calc"})
+        );
+    }
+
+    #[test]
+    fn test_tool_with_same_name_replaces_earlier_one() {
+        let server = calculator().tool(SdkMcpTool::new(
+            "add",
+            "Add, again",
+            json!({}),
+            |_args| async { Ok(ToolResult::text("0")) },
+        ));
+        assert_eq!(server.tools().len(), 2);
+        assert!(server
+            .tools()
+            .iter()
+            .any(|tool| tool.description == "Add, again"));
+    }
+
+    #[tokio::test]
+    async fn test_initialize() {
+        let response = calculator()
+            .handle_message(json!({"jsonrpc": "2.0", "id": 0, "method": "initialize"}))
+            .await;
+        assert_eq!(response["id"], 0);
+        assert_eq!(response["result"]["protocolVersion"], MCP_PROTOCOL_VERSION);
+        assert_eq!(
+            response["result"]["serverInfo"],
+            json!({"name": "calc", "version": "2.0.0"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tools_list() {
+        let response = calculator()
+            .handle_message(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .await;
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["name"], "add");
+        assert_eq!(tools[0]["description"], "Add two numbers");
+        assert_eq!(tools[0]["inputSchema"]["required"], json!(["a", "b"]));
+    }
+
+    #[tokio::test]
+    async fn test_tools_call() {
+        let response = calculator()
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": {"name": "add", "arguments": {"a": 2, "b": 3}},
+            }))
+            .await;
+        assert_eq!(
+            response,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "result": {"content": [{"type": "text", "text": "5"}]},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tools_call_handler_error_is_reported_as_tool_error() {
+        let response = calculator()
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": {"name": "divide", "arguments": {"a": 1, "b": 0}},
+            }))
+            .await;
+        assert_eq!(response["result"]["isError"], true);
+        assert!(response["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Division by zero"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool_and_method() {
+        let server = calculator();
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 4,
+                "method": "tools/call",
+                "params": {"name": "sqrt", "arguments": {}},
+            }))
+            .await;
+        assert_eq!(response["error"]["code"], jsonrpc::INVALID_PARAMS);
+
+        let response = server
+            .handle_message(json!({"jsonrpc": "2.0", "id": 5, "method": "resources/list"}))
+            .await;
+        assert_eq!(response["error"]["code"], jsonrpc::METHOD_NOT_FOUND);
+
+        let response = server.handle_message(json!({"id": 6})).await;
+        assert_eq!(response["id"], 6);
+        assert_eq!(response["error"]["code"], jsonrpc::INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_jsonrpc_response_into_result() {
+        let ok = JsonRpcResponse::success(json!(1), json!({"ok": true}));
+        assert_eq!(ok.into_result().unwrap(), json!({"ok": true}));
+
+        let err = JsonRpcResponse::error(json!(1), jsonrpc::INTERNAL_ERROR, "boom");
+        assert_eq!(err.into_result().unwrap_err().message, "boom");
+
+        let notification = JsonRpcRequest::notification("notifications/initialized", None);
+        assert_eq!(
+            serde_json::to_value(notification).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "notifications/initialized"})
+        );
+    }
+}
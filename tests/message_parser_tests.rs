@@ -1,5 +1,7 @@
+use claude_agent_sdk::error::AgentError;
 use claude_agent_sdk::message_parser::*;
-use serde_json::Value;
+use claude_agent_sdk::types::TextBlock;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 
 #[cfg(test)]
@@ -207,4 +209,297 @@ mod tests {
         assert_eq!(partial_tool_use_message.message_id, deserialized.message_id);
         assert_eq!(partial_tool_use_message.tool_name, deserialized.tool_name);
     }
+
+    #[test]
+    fn test_parse_user_message() {
+        let message = parse_message(&json!({
+            "type": "user",
+            "message": {"role": "user", "content": "Hello"},
+            "parent_tool_use_id": null,
+            "session_id": "s1"
+        }))
+        .unwrap();
+        match message {
+            Message::User(user) => {
+                assert!(matches!(&user.content, UserContent::Text(text) if text == "Hello"));
+                assert_eq!(user.parent_tool_use_id, None);
+            }
+            other => panic!("Expected user message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_user_message_with_blocks() {
+        let message = parse_message(&json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "first"},
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "ok", "is_error": false},
+                    {"type": "text", "text": "second"}
+                ]
+            },
+            "parent_tool_use_id": "toolu_0"
+        }))
+        .unwrap();
+        match message {
+            Message::User(user) => {
+                assert_eq!(user.content.text(), "first\nsecond");
+                assert_eq!(user.parent_tool_use_id.as_deref(), Some("toolu_0"));
+                let UserContent::Blocks(blocks) = &user.content else {
+                    panic!("Expected content blocks");
+                };
+                assert_eq!(blocks.len(), 3);
+                match &blocks[1] {
+                    ContentBlock::ToolResult(result) => {
+                        assert_eq!(result.tool_use_id, "toolu_1");
+                        assert_eq!(result.content, Some(json!("ok")));
+                        assert_eq!(result.is_error, Some(false));
+                    }
+                    other => panic!("Expected tool result, got {:?}", other),
+                }
+            }
+            other => panic!("Expected user message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_assistant_message() {
+        let message = parse_message(&json!({
+            "type": "assistant",
+            "message": {
+                "id": "msg_1",
+                "role": "assistant",
+                "model": "claude-sonnet-4-5",
+                "content": [
+                    {"type": "thinking", "thinking": "Let me look", "signature": "sig"},
+                    {"type": "text", "text": "Listing files"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}}
+                ],
+                "stop_reason": "tool_use"
+            },
+            "parent_tool_use_id": null,
+            "session_id": "s1"
+        }))
+        .unwrap();
+        let Message::Assistant(assistant) = message else {
+            panic!("Expected assistant message");
+        };
+        assert_eq!(assistant.model, "claude-sonnet-4-5");
+        assert_eq!(assistant.content.len(), 3);
+        assert!(matches!(&assistant.content[0], ContentBlock::Thinking(b) if b.signature == "sig"));
+        assert!(
+            matches!(&assistant.content[1], ContentBlock::Text(b) if b.text == "Listing files")
+        );
+        match &assistant.content[2] {
+            ContentBlock::ToolUse(tool_use) => {
+                assert_eq!(tool_use.name, "Bash");
+                assert_eq!(tool_use.input["command"], "ls");
+            }
+            other => panic!("Expected tool use, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_system_message() {
+        let raw = json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "s1",
+            "tools": ["Bash", "Read"],
+            "model": "claude-sonnet-4-5"
+        });
+        let Message::System(system) = parse_message(&raw).unwrap() else {
+            panic!("Expected system message");
+        };
+        assert_eq!(system.subtype, "init");
+        assert_eq!(system.data["tools"], json!(["Bash", "Read"]));
+        assert_eq!(system.data["session_id"], "s1");
+    }
+
+    #[test]
+    fn test_parse_result_message() {
+        let message = parse_message(&json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1200,
+            "duration_api_ms": 900,
+            "is_error": false,
+            "num_turns": 2,
+            "session_id": "s1",
+            "total_cost_usd": 0.0123,
+            "usage": {"input_tokens": 10, "output_tokens": 20},
+            "result": "Done"
+        }))
+        .unwrap();
+        let Message::Result(result) = message else {
+            panic!("Expected result message");
+        };
+        assert_eq!(result.subtype, "success");
+        assert_eq!(result.num_turns, 2);
+        assert_eq!(result.total_cost_usd, Some(0.0123));
+        assert_eq!(result.result.as_deref(), Some("Done"));
+        let usage = result.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (10, 20));
+        assert_eq!(usage.cache_read_input_tokens, 0);
+    }
+
+    #[test]
+    fn test_parse_stream_event() {
+        let message = parse_message(&json!({
+            "type": "stream_event",
+            "uuid": "u1",
+            "session_id": "s1",
+            "event": {"type": "message_start"},
+            "parent_tool_use_id": null
+        }))
+        .unwrap();
+        let Message::StreamEvent(event) = message else {
+            panic!("Expected stream event");
+        };
+        assert_eq!(event.event["type"], "message_start");
+    }
+
+    #[test]
+    fn test_parse_errors_carry_raw_json() {
+        let raw = json!({"type": "assistant", "content": "flat"});
+        let err = parse_message(&raw).unwrap_err();
+        assert_eq!(err.reason, "Message is missing a 'message' object");
+        assert_eq!(err.raw, raw);
+
+        let raw = json!({"type": "result", "subtype": "success"});
+        let err = parse_message(&raw).unwrap_err();
+        assert!(err.reason.starts_with("Invalid result message"));
+        assert_eq!(err.raw, raw);
+
+        let err = parse_message(&json!({"subtype": "init"})).unwrap_err();
+        assert_eq!(err.reason, "Message is missing a 'type' field");
+        let err = parse_message(&json!("text")).unwrap_err();
+        assert_eq!(err.reason, "Message must be a JSON object");
+
+        let err = parse_message_strict(&json!({"type": "telemetry"})).unwrap_err();
+        assert_eq!(err.reason, "Unknown message type: telemetry");
+    }
+
+    #[test]
+    fn test_parse_unknown_message_type() {
+        let raw = json!({"type": "rate_limit", "retry_after_ms": 500});
+        match parse_message(&raw).unwrap() {
+            Message::Unknown {
+                type_name,
+                raw: unknown_raw,
+            } => {
+                assert_eq!(type_name, "rate_limit");
+                assert_eq!(unknown_raw, raw);
+            }
+            other => panic!("Expected unknown message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_unknown_content_block() {
+        let raw = json!({
+            "type": "assistant",
+            "message": {
+                "model": "claude-sonnet-4-5",
+                "content": [
+                    {"type": "text", "text": "Searching"},
+                    {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search"}
+                ]
+            }
+        });
+        let Message::Assistant(assistant) = parse_message(&raw).unwrap() else {
+            panic!("Expected assistant message");
+        };
+        match &assistant.content[1] {
+            ContentBlock::Unknown { raw: block } => assert_eq!(block["name"], "web_search"),
+            other => panic!("Expected unknown block, got {:?}", other),
+        }
+
+        let err = parse_message_strict(&raw).unwrap_err();
+        assert_eq!(err.reason, "Unknown content block type: server_tool_use");
+        assert_eq!(err.raw, raw);
+    }
+
+    #[test]
+    fn test_strict_parse_checks_user_blocks() {
+        let raw = json!({
+            "type": "user",
+            "message": {"role": "user", "content": [{"type": "search_result", "source": "x"}]}
+        });
+        assert!(parse_message(&raw).is_ok());
+        let err = parse_message_strict(&raw).unwrap_err();
+        assert_eq!(err.reason, "Unknown content block type: search_result");
+    }
+
+    #[test]
+    fn test_user_message_builders() {
+        assert_eq!(
+            serde_json::to_value(UserMessage::text("hi")).unwrap(),
+            json!({"content": "hi", "parent_tool_use_id": null})
+        );
+        assert_eq!(
+            serde_json::to_value(UserMessage::tool_result("toolu_1", "42", false)).unwrap(),
+            json!({
+                "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "42", "is_error": false}
+                ],
+                "parent_tool_use_id": null
+            })
+        );
+        let message = UserMessage::with_blocks(vec![
+            ContentBlock::Text(TextBlock {
+                text: "a".to_string(),
+            }),
+            ContentBlock::Text(TextBlock {
+                text: "b".to_string(),
+            }),
+        ])
+        .parent_tool_use_id("toolu_0");
+        assert_eq!(message.content.text(), "a\nb");
+        assert_eq!(message.content.blocks().len(), 2);
+        assert_eq!(message.parent_tool_use_id.as_deref(), Some("toolu_0"));
+    }
+
+    #[test]
+    fn test_known_block_with_bad_fields_is_an_error() {
+        let err = parse_message(&json!({
+            "type": "assistant",
+            "message": {"model": "m", "content": [{"type": "text"}]}
+        }))
+        .unwrap_err();
+        assert!(err.reason.starts_with("Invalid content block"));
+    }
+
+    #[test]
+    fn test_unknown_variants_serialize_raw_json() {
+        let block_raw = json!({"type": "redacted_thinking", "data": "abc"});
+        let block: ContentBlock = serde_json::from_value(block_raw.clone()).unwrap();
+        assert!(matches!(block, ContentBlock::Unknown { .. }));
+        assert_eq!(serde_json::to_value(&block).unwrap(), block_raw);
+
+        let message_raw = json!({"type": "keep_alive"});
+        let message: Message = serde_json::from_value(message_raw.clone()).unwrap();
+        assert_eq!(message.message_id(), "unknown_message");
+        assert_eq!(serde_json::to_value(&message).unwrap(), message_raw);
+
+        // Known kinds keep their tagged shape
+        let text: ContentBlock =
+            serde_json::from_value(json!({"type": "text", "text": "hi"})).unwrap();
+        assert_eq!(
+            serde_json::to_value(&text).unwrap(),
+            json!({"type": "text", "text": "hi"})
+        );
+    }
+
+    #[test]
+    fn test_parse_error_converts_to_agent_error() {
+        let raw = json!({"type": "user"});
+        let err: AgentError = parse_message(&raw).unwrap_err().into();
+        match err {
+            AgentError::MessageParse(parse_error) => assert_eq!(parse_error.raw, raw),
+            other => panic!("Expected MessageParse, got {:?}", other),
+        }
+    }
 }
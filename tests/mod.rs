@@ -1,16 +1,40 @@
 #[cfg(test)]
 mod client_tests;
 #[cfg(test)]
+mod content_tests;
+#[cfg(test)]
+mod control_tests;
+#[cfg(test)]
 mod error_tests;
 #[cfg(test)]
+mod hooks_tests;
+#[cfg(test)]
 mod integration_tests;
 #[cfg(test)]
 mod internal_query_tests;
 #[cfg(test)]
+mod mcp_client_tests;
+#[cfg(test)]
+mod mcp_config_tests;
+#[cfg(test)]
+mod mcp_server_tests;
+#[cfg(test)]
 mod message_parser_tests;
 #[cfg(test)]
+mod options_tests;
+#[cfg(test)]
+mod partial_tests;
+#[cfg(test)]
+mod query_tests;
+#[cfg(test)]
+mod schema_tests;
+#[cfg(test)]
 mod streaming_tests;
 #[cfg(test)]
 mod test_utils;
 #[cfg(test)]
+mod tool_tests;
+#[cfg(test)]
 mod transport_tests;
+#[cfg(test)]
+mod usage_tests;
@@ -0,0 +1,144 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::internal::query::{PermissionResult, PermissionResultAllow};
+use claude_agent_sdk::mcp::McpServerConfig;
+use claude_agent_sdk::types::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|index| args.get(index + 1))
+            .map(String::as_str)
+    }
+
+    #[test]
+    fn test_default_options_produce_no_args() {
+        let options = ClaudeAgentOptions::new();
+        assert!(options.to_cli_args().is_empty());
+    }
+
+    #[test]
+    fn test_basic_options_to_cli_args() {
+        let options = ClaudeAgentOptions::new()
+            .model("claude-sonnet-4-5")
+            .max_turns(3)
+            .allowed_tools(["Read", "Write"])
+            .disallowed_tools(["Bash"])
+            .permission_mode(PermissionMode::AcceptEdits);
+        let args = options.to_cli_args();
+
+        assert_eq!(flag_value(&args, "--model"), Some("claude-sonnet-4-5"));
+        assert_eq!(flag_value(&args, "--max-turns"), Some("3"));
+        assert_eq!(flag_value(&args, "--allowedTools"), Some("Read,Write"));
+        assert_eq!(flag_value(&args, "--disallowedTools"), Some("Bash"));
+        assert_eq!(flag_value(&args, "--permission-mode"), Some("acceptEdits"));
+    }
+
+    #[test]
+    fn test_can_use_tool_prompts_over_stdio() {
+        let options = ClaudeAgentOptions::new().can_use_tool(|_, _, _| async {
+            Ok(PermissionResult::Allow(PermissionResultAllow::default()))
+        });
+        assert!(options.validate().is_ok());
+        let args = options.to_cli_args();
+        assert_eq!(flag_value(&args, "--permission-prompt-tool"), Some("stdio"));
+        assert!(format!("{:?}", options).contains("<callback>"));
+
+        let conflicting = options.permission_prompt_tool_name("mcp__auth__prompt");
+        assert!(matches!(
+            conflicting.validate(),
+            Err(AgentError::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_system_prompt_variants() {
+        let text = ClaudeAgentOptions::new().system_prompt("Be terse.");
+        assert_eq!(
+            flag_value(&text.to_cli_args(), "--system-prompt"),
+            Some("Be terse.")
+        );
+
+        let preset = ClaudeAgentOptions::new().system_prompt(SystemPrompt::Preset {
+            preset: "claude_code".to_string(),
+            append: None,
+        });
+        assert!(preset.to_cli_args().is_empty());
+
+        let appended = ClaudeAgentOptions::new().system_prompt(SystemPrompt::Preset {
+            preset: "claude_code".to_string(),
+            append: Some("Extra rules.".to_string()),
+        });
+        let args = appended.to_cli_args();
+        assert_eq!(
+            flag_value(&args, "--append-system-prompt"),
+            Some("Extra rules.")
+        );
+        assert!(!args.contains(&"--system-prompt".to_string()));
+    }
+
+    #[test]
+    fn test_setting_sources_and_flags() {
+        let options = ClaudeAgentOptions::new()
+            .setting_sources([SettingSource::User, SettingSource::Project])
+            .include_partial_messages(true)
+            .continue_conversation(true)
+            .fork_session(true)
+            .resume("session-123")
+            .add_dir("/tmp/a")
+            .add_dir("/tmp/b");
+        let args = options.to_cli_args();
+
+        assert_eq!(flag_value(&args, "--setting-sources"), Some("user,project"));
+        assert_eq!(flag_value(&args, "--resume"), Some("session-123"));
+        assert!(args.contains(&"--include-partial-messages".to_string()));
+        assert!(args.contains(&"--continue".to_string()));
+        assert!(args.contains(&"--fork-session".to_string()));
+        assert_eq!(args.iter().filter(|arg| *arg == "--add-dir").count(), 2);
+    }
+
+    #[test]
+    fn test_agents_and_mcp_servers_serialize_to_json() {
+        let options = ClaudeAgentOptions::new()
+            .agent(
+                "reviewer",
+                AgentDefinition::new("Reviews code", "You review code.").tools(["Read"]),
+            )
+            .mcp_server("calc", McpServerConfig::stdio("calc-server"));
+        let args = options.to_cli_args();
+
+        let agents: serde_json::Value =
+            serde_json::from_str(flag_value(&args, "--agents").unwrap()).unwrap();
+        assert_eq!(agents["reviewer"]["description"], "Reviews code");
+        assert_eq!(agents["reviewer"]["tools"], serde_json::json!(["Read"]));
+        assert!(agents["reviewer"].get("model").is_none());
+
+        let mcp_config: serde_json::Value =
+            serde_json::from_str(flag_value(&args, "--mcp-config").unwrap()).unwrap();
+        assert_eq!(mcp_config["mcpServers"]["calc"]["command"], "calc-server");
+    }
+
+    #[test]
+    fn test_extra_args() {
+        let options = ClaudeAgentOptions::new()
+            .extra_arg("debug-to-stderr", None)
+            .extra_arg("output-style", Some("concise".to_string()));
+        let args = options.to_cli_args();
+
+        assert!(args.contains(&"--debug-to-stderr".to_string()));
+        assert_eq!(flag_value(&args, "--output-style"), Some("concise"));
+    }
+
+    #[test]
+    fn test_permission_mode_serialization() {
+        assert_eq!(
+            serde_json::to_value(PermissionMode::BypassPermissions).unwrap(),
+            "bypassPermissions"
+        );
+        let mode: PermissionMode = serde_json::from_str("\"plan\"").unwrap();
+        assert_eq!(mode, PermissionMode::Plan);
+    }
+}
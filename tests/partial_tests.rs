@@ -0,0 +1,299 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::message_parser::parse_message;
+use claude_agent_sdk::partial::{Delta, MessageAccumulator, MessageUpdate};
+use claude_agent_sdk::types::ContentBlock;
+use serde_json::{json, Value};
+
+fn message_start() -> Value {
+    json!({
+        "type": "message_start",
+        "message": {
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [],
+            "stop_reason": null,
+            "usage": {"input_tokens": 12, "output_tokens": 1, "cache_read_input_tokens": 100}
+        }
+    })
+}
+
+fn block_start(index: u64, block: Value) -> Value {
+    json!({"type": "content_block_start", "index": index, "content_block": block})
+}
+
+fn delta(index: u64, delta: Value) -> Value {
+    json!({"type": "content_block_delta", "index": index, "delta": delta})
+}
+
+fn block_stop(index: u64) -> Value {
+    json!({"type": "content_block_stop", "index": index})
+}
+
+// Feeds every event and returns the updates they produced
+fn feed(accumulator: &mut MessageAccumulator, events: &[Value]) -> Vec<MessageUpdate> {
+    events
+        .iter()
+        .filter_map(|event| accumulator.push_event(event).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulates_thinking_text_and_tool_use() {
+        let mut accumulator = MessageAccumulator::new();
+        let updates = feed(
+            &mut accumulator,
+            &[
+                message_start(),
+                block_start(
+                    0,
+                    json!({"type": "thinking", "thinking": "", "signature": ""}),
+                ),
+                delta(
+                    0,
+                    json!({"type": "thinking_delta", "thinking": "Three jokes"}),
+                ),
+                delta(
+                    0,
+                    json!({"type": "thinking_delta", "thinking": ", then one."}),
+                ),
+                delta(0, json!({"type": "signature_delta", "signature": "sig"})),
+                block_stop(0),
+                block_start(1, json!({"type": "text", "text": ""})),
+                delta(1, json!({"type": "text_delta", "text": "Let me "})),
+                delta(1, json!({"type": "text_delta", "text": "check."})),
+                block_stop(1),
+                block_start(
+                    2,
+                    json!({"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {}}),
+                ),
+                delta(2, json!({"type": "input_json_delta", "partial_json": ""})),
+                delta(
+                    2,
+                    json!({"type": "input_json_delta", "partial_json": "{\"command\": \"sl"}),
+                ),
+            ],
+        );
+        assert!(matches!(updates[0], MessageUpdate::MessageStarted));
+        assert!(matches!(
+            &updates[5],
+            MessageUpdate::BlockCompleted { index: 0, block: ContentBlock::Thinking(t) }
+                if t.thinking == "Three jokes, then one." && t.signature == "sig"
+        ));
+        assert_eq!(accumulator.partial_json(2), Some("{\"command\": \"sl"));
+
+        // The message can be inspected while it is still streaming
+        let message = accumulator.message().unwrap();
+        assert_eq!(message.id.as_deref(), Some("msg_1"));
+        assert_eq!(message.model, "claude-sonnet-4-5");
+        assert_eq!(message.content.len(), 3);
+        assert!(matches!(&message.content[1], ContentBlock::Text(t) if t.text == "Let me check."));
+        assert!(!accumulator.is_complete());
+
+        let updates = feed(
+            &mut accumulator,
+            &[
+                delta(
+                    2,
+                    json!({"type": "input_json_delta", "partial_json": "eep 5\"}"}),
+                ),
+                block_stop(2),
+            ],
+        );
+        match &updates[1] {
+            MessageUpdate::BlockCompleted {
+                index: 2,
+                block: ContentBlock::ToolUse(tool_use),
+            } => {
+                assert_eq!(tool_use.id, "toolu_1");
+                assert_eq!(tool_use.input["command"], "sleep 5");
+            }
+            other => panic!("Expected tool use completion, got {:?}", other),
+        }
+        assert_eq!(accumulator.partial_json(2), None);
+    }
+
+    #[test]
+    fn test_message_delta_sets_stop_reason_and_usage() {
+        let mut accumulator = MessageAccumulator::new();
+        let updates = feed(
+            &mut accumulator,
+            &[
+                message_start(),
+                json!({"type": "ping"}),
+                block_start(0, json!({"type": "text", "text": ""})),
+                block_stop(0),
+                json!({
+                    "type": "message_delta",
+                    "delta": {"stop_reason": "end_turn", "stop_sequence": null},
+                    "usage": {"output_tokens": 42}
+                }),
+                json!({"type": "message_stop"}),
+            ],
+        );
+
+        // `ping` produces no update
+        assert_eq!(updates.len(), 5);
+        assert!(matches!(
+            &updates[3],
+            MessageUpdate::MessageDelta { stop_reason: Some(reason) } if reason == "end_turn"
+        ));
+        match &updates[4] {
+            MessageUpdate::MessageCompleted { stop_reason, usage } => {
+                assert_eq!(stop_reason.as_deref(), Some("end_turn"));
+                let usage = usage.unwrap();
+                assert_eq!(usage.input_tokens, 12);
+                assert_eq!(usage.output_tokens, 42);
+                assert_eq!(usage.cache_read_input_tokens, 100);
+            }
+            other => panic!("Expected completion, got {:?}", other),
+        }
+        assert!(accumulator.is_complete());
+        assert_eq!(accumulator.stop_reason(), Some("end_turn"));
+        assert_eq!(accumulator.stop_sequence(), None);
+
+        let message = accumulator.take_message().unwrap();
+        assert_eq!(message.usage.unwrap().output_tokens, 42);
+        assert!(accumulator.message().is_none());
+        assert!(!accumulator.is_complete());
+    }
+
+    #[test]
+    fn test_push_message_reads_stream_events_from_the_cli() {
+        let frame = |event: Value| {
+            parse_message(&json!({
+                "type": "stream_event",
+                "uuid": "u1",
+                "session_id": "s1",
+                "event": event,
+                "parent_tool_use_id": "toolu_parent"
+            }))
+            .unwrap()
+        };
+        let mut accumulator = MessageAccumulator::new();
+        accumulator.push_message(&frame(message_start())).unwrap();
+        accumulator
+            .push_message(&frame(block_start(0, json!({"type": "text", "text": ""}))))
+            .unwrap();
+        let update = accumulator
+            .push_message(&frame(delta(
+                0,
+                json!({"type": "text_delta", "text": "Hello"}),
+            )))
+            .unwrap();
+        assert!(matches!(
+            update,
+            Some(MessageUpdate::BlockDelta { index: 0, delta: Delta::Text(text) }) if text == "Hello"
+        ));
+
+        let message = accumulator.message().unwrap();
+        assert_eq!(message.parent_tool_use_id.as_deref(), Some("toolu_parent"));
+
+        // Other messages are ignored
+        let result = parse_message(&json!({
+            "type": "system", "subtype": "init", "data": {}
+        }))
+        .unwrap();
+        assert!(accumulator.push_message(&result).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unknown_blocks_and_deltas_pass_through() {
+        let mut accumulator = MessageAccumulator::new();
+        let updates = feed(
+            &mut accumulator,
+            &[
+                message_start(),
+                block_start(
+                    0,
+                    json!({"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {}}),
+                ),
+                delta(
+                    0,
+                    json!({"type": "input_json_delta", "partial_json": "{\"query\": \"rust\"}"}),
+                ),
+                delta(0, json!({"type": "citations_delta", "citation": {}})),
+                block_stop(0),
+            ],
+        );
+        assert!(matches!(
+            &updates[3],
+            MessageUpdate::BlockDelta { delta: Delta::Unknown { raw }, .. } if raw["type"] == "citations_delta"
+        ));
+        match &updates[4] {
+            MessageUpdate::BlockCompleted {
+                block: ContentBlock::Unknown { raw },
+                ..
+            } => assert_eq!(raw["input"]["query"], "rust"),
+            other => panic!("Expected unknown block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_events_are_errors() {
+        let mut accumulator = MessageAccumulator::new();
+        assert!(accumulator
+            .push_event(&block_start(0, json!({"type": "text", "text": ""})))
+            .is_err());
+
+        accumulator.push_event(&message_start()).unwrap();
+        let early_delta = delta(0, json!({"type": "text_delta", "text": "x"}));
+        match accumulator.push_event(&early_delta) {
+            Err(AgentError::MessageParse(err)) => {
+                assert_eq!(err.raw, early_delta);
+                assert!(err.reason.contains("before it started"), "{}", err.reason);
+            }
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+        assert!(accumulator
+            .push_event(&block_start(1, json!({"type": "text", "text": ""})))
+            .is_err());
+    }
+
+    #[test]
+    fn test_null_usage_fields_are_skipped() {
+        let mut start = message_start();
+        start["message"]["usage"]["cache_creation_input_tokens"] = Value::Null;
+        start["message"]["usage"]["server_tool_use"] = Value::Null;
+        let mut accumulator = MessageAccumulator::new();
+        feed(
+            &mut accumulator,
+            &[
+                start,
+                json!({
+                    "type": "message_delta",
+                    "delta": {"stop_reason": "end_turn"},
+                    "usage": {"output_tokens": 9, "cache_read_input_tokens": null}
+                }),
+            ],
+        );
+        let usage = accumulator.usage().unwrap();
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 9);
+        assert_eq!(usage.cache_read_input_tokens, 100);
+        assert_eq!(usage.cache_creation_input_tokens, 0);
+    }
+
+    #[test]
+    fn test_message_start_resets_the_accumulator() {
+        let mut accumulator = MessageAccumulator::new();
+        feed(
+            &mut accumulator,
+            &[
+                message_start(),
+                block_start(0, json!({"type": "text", "text": "first"})),
+                json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}}),
+                json!({"type": "message_stop"}),
+                message_start(),
+            ],
+        );
+        assert!(accumulator.message().unwrap().content.is_empty());
+        assert_eq!(accumulator.stop_reason(), None);
+        assert!(!accumulator.is_complete());
+    }
+}
@@ -0,0 +1,164 @@
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::types::{ClaudeAgentOptions, ContentBlock, Message, UserMessage};
+use claude_agent_sdk::{query, query_with_stream};
+use futures::StreamExt;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Also declared by tests/mod.rs when every test file is built as one crate
+#[allow(clippy::duplicate_mod)]
+#[path = "test_utils.rs"]
+mod test_utils;
+
+#[cfg(unix)]
+use test_utils::stub_cli_script;
+#[cfg(target_os = "linux")]
+use test_utils::wait_until_gone;
+
+/// Writes a fake `claude` that answers the initialize request, saves the
+/// prompt next to itself as `prompt.json` and then runs `body`.
+#[cfg(unix)]
+fn stub_cli(body: &str) -> PathBuf {
+    stub_cli_script(
+        "2.1.0 (Claude Code)",
+        &format!(
+            r#"read -r init
+id=$(printf '%s' "$init" | sed 's/.*"request_id":"\([^"]*\)".*/\1/')
+echo "{{\"type\":\"control_response\",\"response\":{{\"subtype\":\"success\",\"request_id\":\"$id\",\"response\":{{}}}}}}"
+read -r prompt
+printf '%s\n' "$prompt" > "$(dirname "$0")/prompt.json"
+{}"#,
+            body
+        ),
+    )
+}
+
+const ASSISTANT: &str =
+    r#"{"type":"assistant","message":{"model":"m","content":[{"type":"text","text":"Paris"}]}}"#;
+const RESULT: &str = r#"{"type":"result","subtype":"success","duration_ms":5,"duration_api_ms":4,"is_error":false,"num_turns":1,"session_id":"s1","result":"Paris"}"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_query_streams_until_result() {
+        let cli = stub_cli(&format!(
+            "echo '{}'\necho '{}'\ncat > /dev/null",
+            ASSISTANT, RESULT
+        ));
+        let options = ClaudeAgentOptions::new().cli_path(&cli);
+        let messages: Vec<Message> = query("What is the capital of France?", options)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(messages.len(), 2);
+        match &messages[0] {
+            Message::Assistant(assistant) => {
+                assert!(matches!(&assistant.content[0], ContentBlock::Text(t) if t.text == "Paris"))
+            }
+            other => panic!("Expected assistant message, got {:?}", other),
+        }
+        assert!(matches!(&messages[1], Message::Result(r) if r.result.as_deref() == Some("Paris")));
+
+        let prompt: Value = serde_json::from_str(
+            &std::fs::read_to_string(cli.with_file_name("prompt.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(prompt["type"], "user");
+        assert_eq!(
+            prompt["message"]["content"],
+            "What is the capital of France?"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_query_with_stream_ends_when_input_ends() {
+        // Answers every prompt and exits once stdin is closed
+        let cli = stub_cli(&format!(
+            "echo '{}'\nwhile read -r line; do echo '{}'; done",
+            RESULT, RESULT
+        ));
+        let prompts = futures::stream::iter(["one", "two", "three"].map(UserMessage::text));
+        let results: Vec<Result<Message, AgentError>> =
+            tokio::time::timeout(Duration::from_secs(10), async {
+                query_with_stream(prompts, ClaudeAgentOptions::new().cli_path(&cli))
+                    .await
+                    .unwrap()
+                    .collect()
+                    .await
+            })
+            .await
+            .expect("the CLI should exit once the prompts run out");
+
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|message| matches!(message, Ok(Message::Result(_)))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_query_with_stream_reports_exit_before_result() {
+        let cli = stub_cli("echo 'boom' >&2\nexit 3");
+        let prompts = futures::stream::iter([UserMessage::text("hi")]);
+        let results: Vec<Result<Message, AgentError>> =
+            query_with_stream(prompts, ClaudeAgentOptions::new().cli_path(cli))
+                .await
+                .unwrap()
+                .collect()
+                .await;
+        assert_eq!(results.len(), 1);
+        let err = results[0].as_ref().unwrap_err().to_string();
+        assert!(err.contains("boom"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_query_reports_cli_exit() {
+        let cli = stub_cli(&format!("echo '{}'\necho 'boom' >&2\nexit 3", ASSISTANT));
+        let results: Vec<Result<Message, AgentError>> =
+            query("hi", ClaudeAgentOptions::new().cli_path(cli))
+                .await
+                .unwrap()
+                .collect()
+                .await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        let err = results[1].as_ref().unwrap_err().to_string();
+        assert!(err.contains("boom"), "{}", err);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_dropping_query_stream_kills_cli() {
+        let cli = stub_cli(&format!(
+            "echo $$ > \"$(dirname \"$0\")/pid\"\necho '{}'\nexec sleep 30",
+            ASSISTANT
+        ));
+        let mut messages = query("hi", ClaudeAgentOptions::new().cli_path(&cli))
+            .await
+            .unwrap();
+        assert!(messages.next().await.unwrap().is_ok());
+        let pid = std::fs::read_to_string(cli.with_file_name("pid")).unwrap();
+
+        drop(messages);
+        assert!(wait_until_gone(pid.trim()).await);
+    }
+
+    #[tokio::test]
+    async fn test_query_without_cli() {
+        let options = ClaudeAgentOptions::new().cli_path("/definitely/not/here/claude");
+        match query("hi", options).await {
+            Err(AgentError::CLINotFound { .. }) => {}
+            Err(other) => panic!("Expected CLINotFound, got {:?}", other),
+            Ok(_) => panic!("Expected CLINotFound"),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+#![cfg(feature = "derive")]
+
+use claude_agent_sdk::mcp::{SdkMcpServer, SdkMcpTool, ToolResult};
+use claude_agent_sdk::schema::InputSchema;
+use claude_agent_sdk::types::ToolDefinition;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Search the documentation.
+#[derive(Deserialize, InputSchema)]
+#[allow(dead_code)]
+struct SearchInput {
+    /// Words to look for
+    query: String,
+    /// Maximum number of hits
+    limit: Option<u32>,
+    tags: Vec<String>,
+    #[serde(default)]
+    exact: bool,
+}
+
+/// How results are ordered.
+#[derive(Deserialize, InputSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(dead_code)]
+enum SortOrder {
+    MostRecent,
+    Relevance,
+    #[serde(rename = "alpha")]
+    Alphabetical,
+}
+
+#[derive(Deserialize, InputSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ListInput {
+    sort_order: SortOrder,
+    page_size: Option<u8>,
+    #[serde(rename = "q")]
+    filter: Option<String>,
+    #[serde(skip)]
+    internal: u32,
+    labels: HashMap<String, String>,
+}
+
+#[derive(Deserialize, InputSchema)]
+#[allow(dead_code)]
+struct Wrapper<T> {
+    inner: T,
+    note: Option<Box<f64>>,
+}
+
+#[derive(Deserialize, InputSchema)]
+#[allow(dead_code)]
+struct Paging {
+    page: u32,
+    per_page: Option<u32>,
+}
+
+#[derive(Deserialize, InputSchema)]
+#[allow(dead_code)]
+struct FlattenedInput {
+    query: String,
+    #[serde(flatten)]
+    paging: Paging,
+    #[serde(flatten)]
+    sort: Option<SortSpec>,
+    #[serde(flatten)]
+    extra: HashMap<String, String>,
+}
+
+#[derive(Deserialize, InputSchema)]
+#[allow(dead_code)]
+struct SortSpec {
+    sort_by: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_schemas() {
+        assert_eq!(String::input_schema(), json!({"type": "string"}));
+        assert_eq!(bool::input_schema(), json!({"type": "boolean"}));
+        assert_eq!(i64::input_schema(), json!({"type": "integer"}));
+        assert_eq!(
+            u32::input_schema(),
+            json!({"type": "integer", "minimum": 0})
+        );
+        assert_eq!(f64::input_schema(), json!({"type": "number"}));
+        assert_eq!(Value::input_schema(), json!({}));
+        assert_eq!(
+            Vec::<f64>::input_schema(),
+            json!({"type": "array", "items": {"type": "number"}})
+        );
+        assert_eq!(Option::<String>::input_schema(), json!({"type": "string"}));
+    }
+
+    #[test]
+    fn test_derive_struct_schema() {
+        assert_eq!(
+            SearchInput::input_schema(),
+            json!({
+                "type": "object",
+                "description": "Search the documentation.",
+                "properties": {
+                    "query": {"type": "string", "description": "Words to look for"},
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Maximum number of hits"
+                    },
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "exact": {"type": "boolean"}
+                },
+                "required": ["query", "tags"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_derive_enum_schema() {
+        assert_eq!(
+            SortOrder::input_schema(),
+            json!({
+                "type": "string",
+                "description": "How results are ordered.",
+                "enum": ["most_recent", "relevance", "alpha"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_derive_honours_serde_renames() {
+        let schema = ListInput::input_schema();
+        let mut properties: Vec<&String> =
+            schema["properties"].as_object().unwrap().keys().collect();
+        properties.sort();
+        assert_eq!(properties, ["labels", "pageSize", "q", "sortOrder"]);
+        assert_eq!(schema["required"], json!(["sortOrder", "labels"]));
+        assert_eq!(
+            schema["properties"]["sortOrder"]["enum"],
+            json!(["most_recent", "relevance", "alpha"])
+        );
+        assert_eq!(
+            schema["properties"]["labels"],
+            json!({"type": "object", "additionalProperties": {"type": "string"}})
+        );
+    }
+
+    #[test]
+    fn test_derive_merges_flattened_fields() {
+        let schema = FlattenedInput::input_schema();
+        let mut properties: Vec<&String> =
+            schema["properties"].as_object().unwrap().keys().collect();
+        properties.sort();
+        assert_eq!(properties, ["page", "per_page", "query", "sort_by"]);
+        // An optional flattened struct adds no required fields
+        assert_eq!(schema["required"], json!(["query", "page"]));
+        assert_eq!(schema["additionalProperties"], json!({"type": "string"}));
+
+        // The schema describes what serde accepts
+        let input: FlattenedInput =
+            serde_json::from_value(json!({"query": "q", "page": 2, "color": "red"})).unwrap();
+        assert_eq!(input.paging.page, 2);
+        assert_eq!(input.extra["color"], "red");
+    }
+
+    #[test]
+    fn test_derive_generic_struct() {
+        let schema = Wrapper::<SortOrder>::input_schema();
+        assert_eq!(schema["properties"]["inner"]["type"], "string");
+        assert_eq!(schema["properties"]["note"], json!({"type": "number"}));
+        assert_eq!(schema["required"], json!(["inner"]));
+    }
+
+    #[test]
+    fn test_tool_definition_for_input() {
+        let definition = ToolDefinition::for_input::<SearchInput>("search", "Search the docs");
+        assert_eq!(definition.name, "search");
+        assert_eq!(definition.description, "Search the docs");
+        assert_eq!(definition.input_schema, SearchInput::input_schema());
+    }
+
+    fn search_server() -> SdkMcpServer {
+        SdkMcpServer::new("docs").tool(SdkMcpTool::typed(
+            "search",
+            "Search the docs",
+            |input: SearchInput| async move {
+                Ok(ToolResult::text(format!(
+                    "{} (limit {})",
+                    input.query,
+                    input.limit.unwrap_or(10)
+                )))
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_typed_tool_receives_struct() {
+        let server = search_server();
+        assert_eq!(server.tools()[0].input_schema, SearchInput::input_schema());
+
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": "search", "arguments": {"query": "hooks", "tags": []}}
+            }))
+            .await;
+        assert_eq!(
+            response["result"],
+            json!({"content": [{"type": "text", "text": "hooks (limit 10)"}]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_typed_tool_rejects_invalid_input() {
+        let response = search_server()
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": {"name": "search", "arguments": {"limit": 5}}
+            }))
+            .await;
+        assert_eq!(response["result"]["isError"], true);
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("Invalid input for tool 'search'"));
+        assert!(text.contains("query"));
+    }
+}
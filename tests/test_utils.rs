@@ -1,6 +1,12 @@
+#![allow(dead_code)]
+
 use claude_agent_sdk::transport::Transport;
 use mockall::mock;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
 
 // Mock transport for testing
 mock! {
@@ -14,10 +20,7 @@ mock! {
 
 // Utility functions for testing
 pub fn create_test_user_message(content: &str) -> claude_agent_sdk::types::UserMessage {
-    claude_agent_sdk::types::UserMessage {
-        content: content.to_string(),
-        parent_tool_use_id: None,
-    }
+    claude_agent_sdk::types::UserMessage::text(content)
 }
 
 pub fn create_test_assistant_message(text: &str) -> claude_agent_sdk::types::AssistantMessage {
@@ -29,6 +32,8 @@ pub fn create_test_assistant_message(text: &str) -> claude_agent_sdk::types::Ass
         )],
         model: "claude-3-haiku".to_string(),
         parent_tool_use_id: None,
+        id: None,
+        usage: None,
     }
 }
 
@@ -38,3 +43,168 @@ pub fn create_test_system_message(subtype: &str) -> claude_agent_sdk::types::Sys
         data: std::collections::HashMap::new(),
     }
 }
+
+/// Writes a fake `claude` that reports `version_output` for `--version` and runs `body` otherwise.
+#[cfg(unix)]
+pub fn stub_cli_script(version_output: &str, body: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("claude-stub-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("claude");
+    let script = format!(
+        "#!/bin/sh\nif [ \"$1\" = \"--version\" ]; then echo '{}'; exit 0; fi\n{}\n",
+        version_output, body
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+/// True once `pid` has exited (it may linger as an unreaped zombie).
+#[cfg(target_os = "linux")]
+pub fn process_is_gone(pid: &str) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat
+            .rsplit(')')
+            .next()
+            .map(|rest| rest.trim_start().starts_with('Z'))
+            .unwrap_or(true),
+        Err(_) => true,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub async fn wait_until_gone(pid: &str) -> bool {
+    for _ in 0..50 {
+        if process_is_gone(pid) {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    false
+}
+
+// Fake CLI that answers every control request and lets tests inject frames
+pub struct FakeCliTransport {
+    sent: std::sync::Mutex<Vec<Value>>,
+    frames_tx: mpsc::UnboundedSender<Value>,
+    frames_rx: Mutex<mpsc::UnboundedReceiver<Value>>,
+    fail_control_requests: bool,
+    pub input_ended: AtomicBool,
+    pub closed: AtomicBool,
+}
+
+impl FakeCliTransport {
+    pub fn new(fail_control_requests: bool) -> Arc<Self> {
+        let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+        Arc::new(FakeCliTransport {
+            sent: std::sync::Mutex::new(Vec::new()),
+            frames_tx,
+            frames_rx: Mutex::new(frames_rx),
+            fail_control_requests,
+            input_ended: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn push_frame(&self, frame: Value) {
+        self.frames_tx.send(frame).unwrap();
+    }
+
+    /// Every frame the SDK has sent so far.
+    pub fn sent(&self) -> Vec<Value> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    pub fn control_subtypes(&self) -> Vec<String> {
+        self.sent()
+            .iter()
+            .filter(|frame| frame["type"] == "control_request")
+            .map(|frame| frame["request"]["subtype"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    // Sends a control request as the CLI and waits for the SDK's answer
+    pub async fn ask(&self, request_id: &str, request: Value) -> Value {
+        self.push_frame(json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": request,
+        }));
+        for _ in 0..200 {
+            if let Some(frame) = self
+                .sent()
+                .into_iter()
+                .find(|frame| frame["response"]["request_id"] == request_id)
+            {
+                assert_eq!(frame["type"], "control_response");
+                return frame["response"].clone();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("no control_response for {}", request_id);
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for FakeCliTransport {
+    async fn send(
+        &self,
+        payload: Value,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        self.sent.lock().unwrap().push(payload.clone());
+        if payload["type"] == "control_request" {
+            let request_id = payload["request_id"].clone();
+            let response = if self.fail_control_requests {
+                json!({"subtype": "error", "request_id": request_id, "error": "not supported"})
+            } else {
+                json!({"subtype": "success", "request_id": request_id, "response": {"ok": true}})
+            };
+            self.push_frame(json!({"type": "control_response", "response": response}));
+        }
+        Ok(Value::Null)
+    }
+
+    async fn receive(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        match self.frames_rx.lock().await.recv().await {
+            Some(frame) => Ok(frame),
+            None => Err("stream closed".into()),
+        }
+    }
+
+    async fn end_input(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.input_ended.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.closed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+// Lets a test keep its own handle on the fake while a client owns the transport
+pub struct SharedFakeCli(pub Arc<FakeCliTransport>);
+
+#[async_trait::async_trait]
+impl Transport for SharedFakeCli {
+    async fn send(
+        &self,
+        payload: Value,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        self.0.send(payload).await
+    }
+
+    async fn receive(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        self.0.receive().await
+    }
+
+    async fn end_input(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.0.end_input().await
+    }
+
+    async fn close(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.0.close().await
+    }
+}
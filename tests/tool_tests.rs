@@ -0,0 +1,224 @@
+#![cfg(feature = "derive")]
+
+use async_trait::async_trait;
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::mcp::{SdkMcpServer, SdkMcpTool, ToolContent, ToolResult};
+use claude_agent_sdk::schema::InputSchema;
+use claude_agent_sdk::tool::{Json, Tool, ToolContext, ToolRegistry};
+use claude_agent_sdk::types::ToolUseBlock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Deserialize, InputSchema)]
+struct DivideInput {
+    a: f64,
+    b: f64,
+}
+
+#[derive(Serialize)]
+struct Quotient {
+    value: f64,
+}
+
+struct Divide;
+
+#[async_trait]
+impl Tool for Divide {
+    type Input = DivideInput;
+    type Output = Json<Quotient>;
+
+    fn name(&self) -> &str {
+        "divide"
+    }
+
+    fn description(&self) -> &str {
+        "Divide one number by another"
+    }
+
+    async fn call(
+        &self,
+        input: DivideInput,
+        _ctx: ToolContext,
+    ) -> Result<Json<Quotient>, AgentError> {
+        if input.b == 0.0 {
+            return Err(AgentError::ToolExecutionError(
+                "Division by zero".to_string(),
+            ));
+        }
+        Ok(Json(Quotient {
+            value: input.a / input.b,
+        }))
+    }
+}
+
+#[derive(Deserialize, InputSchema)]
+struct NoInput {}
+
+// Reports the tool use id it was called with
+struct WhoAmI;
+
+#[async_trait]
+impl Tool for WhoAmI {
+    type Input = NoInput;
+    type Output = String;
+
+    fn name(&self) -> &str {
+        "whoami"
+    }
+
+    fn description(&self) -> &str {
+        "Returns the tool use id"
+    }
+
+    async fn call(&self, _input: NoInput, ctx: ToolContext) -> Result<String, AgentError> {
+        Ok(ctx.tool_use_id.unwrap_or_else(|| "none".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ToolRegistry {
+        ToolRegistry::new().register(Divide).register(WhoAmI)
+    }
+
+    fn tool_use(id: &str, name: &str, input: Value) -> ToolUseBlock {
+        let input: HashMap<String, Value> = serde_json::from_value(input).unwrap();
+        ToolUseBlock {
+            id: id.to_string(),
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    #[test]
+    fn test_definitions_come_from_input_types() {
+        let definitions = registry().definitions();
+        let names: Vec<&str> = definitions.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["divide", "whoami"]);
+        assert_eq!(definitions[0].description, "Divide one number by another");
+        assert_eq!(definitions[0].input_schema, DivideInput::input_schema());
+    }
+
+    #[tokio::test]
+    async fn test_run_converts_json_output() {
+        let block = registry()
+            .run(&tool_use("toolu_1", "divide", json!({"a": 9, "b": 3})))
+            .await;
+        assert_eq!(block.tool_use_id, "toolu_1");
+        assert_eq!(block.is_error, Some(false));
+        assert_eq!(
+            block.content,
+            Some(json!([{"type": "text", "text": "{\n  \"value\": 3.0\n}"}]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_passes_tool_use_id() {
+        let block = registry()
+            .run(&tool_use("toolu_2", "whoami", json!({})))
+            .await;
+        assert_eq!(
+            block.content,
+            Some(json!([{"type": "text", "text": "toolu_2"}]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_input_is_reported_to_model() {
+        let block = registry()
+            .run(&tool_use("toolu_3", "divide", json!({"a": "nine"})))
+            .await;
+        assert_eq!(block.is_error, Some(true));
+        let text = block.content.unwrap()[0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(text.starts_with("Invalid input for tool 'divide'"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_error_is_reported_to_model() {
+        let result = registry()
+            .call("divide", json!({"a": 1, "b": 0}), ToolContext::default())
+            .await;
+        assert_eq!(
+            result,
+            ToolResult::error("Tool execution error: Division by zero")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool() {
+        let result = registry()
+            .call("missing", json!({}), ToolContext::default())
+            .await;
+        assert_eq!(result, ToolResult::error("Tool 'missing' not found"));
+    }
+
+    #[tokio::test]
+    async fn test_registry_as_mcp_server() {
+        let server = registry().into_mcp_server("math");
+        assert_eq!(server.name(), "math");
+        assert_eq!(server.tools().len(), 2);
+
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": "divide", "arguments": {"a": 1, "b": 0}}
+            }))
+            .await;
+        assert_eq!(response["result"]["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn test_sdk_tool_from_tool() {
+        let server = SdkMcpServer::new("math").tool(SdkMcpTool::from_tool(Divide));
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": "divide", "arguments": {"a": 1, "b": 4}}
+            }))
+            .await;
+        assert_eq!(
+            response["result"]["content"][0]["text"],
+            "{\n  \"value\": 0.25\n}"
+        );
+    }
+
+    #[test]
+    fn test_image_result_block() {
+        let block = ToolResult::image("aGVsbG8=", "image/png").into_block("toolu_4");
+        assert_eq!(
+            block.content,
+            Some(json!([{
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": "aGVsbG8="}
+            }]))
+        );
+    }
+
+    #[test]
+    fn test_output_conversions() {
+        assert_eq!(ToolResult::from("hi"), ToolResult::text("hi"));
+        assert_eq!(
+            ToolResult::from(json!({"ok": true})),
+            ToolResult::text("{\n  \"ok\": true\n}")
+        );
+        let content = vec![
+            ToolContent::Text {
+                text: "a".to_string(),
+            },
+            ToolContent::Text {
+                text: "b".to_string(),
+            },
+        ];
+        assert_eq!(ToolResult::from(content.clone()).content, content);
+    }
+}
@@ -1,12 +1,32 @@
-use claude_agent_sdk::transport::{SubprocessCLITransport, Transport};
-use serde_json::Value;
-use std::process::Stdio;
-use tokio::process::Command;
+use claude_agent_sdk::error::AgentError;
+use claude_agent_sdk::transport::{
+    check_claude_version, check_claude_version_with_timeout, find_claude_cli,
+    SubprocessCLITransport, Transport,
+};
+use claude_agent_sdk::types::ClaudeAgentOptions;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Also declared by tests/mod.rs when every test file is built as one crate
+#[allow(clippy::duplicate_mod)]
+#[path = "test_utils.rs"]
+mod test_utils;
+
+#[cfg(unix)]
+use test_utils::stub_cli_script;
+#[cfg(target_os = "linux")]
+use test_utils::wait_until_gone;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    fn stub_cli(version_output: &str) -> PathBuf {
+        stub_cli_script(version_output, "exit 0")
+    }
+
     #[tokio::test]
     async fn test_subprocess_transport_creation() {
         // This test would require an actual command to test against
@@ -19,4 +39,204 @@ mod tests {
         // This test would require an actual command to test against
         // For now, we'll just test that the code compiles
     }
+
+    #[test]
+    fn test_find_cli_with_missing_explicit_path() {
+        let missing = PathBuf::from("/definitely/not/here/claude");
+        match find_claude_cli(Some(&missing)) {
+            Err(AgentError::CLINotFound { searched }) => assert_eq!(searched, vec![missing]),
+            other => panic!("expected CLINotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_not_found_lists_searched_paths() {
+        let error = AgentError::CLINotFound {
+            searched: vec![PathBuf::from("/a/claude"), PathBuf::from("/b/claude")],
+        };
+        let msg = error.to_string();
+        assert!(msg.contains("/a/claude, /b/claude"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_cli_with_explicit_path() {
+        let cli = stub_cli("2.0.0 (Claude Code)");
+        assert_eq!(find_claude_cli(Some(&cli)).unwrap(), cli);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supported_cli_version() {
+        let cli = stub_cli("2.1.14 (Claude Code)");
+        assert_eq!(check_claude_version(&cli).unwrap(), "2.1.14");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unsupported_cli_version() {
+        let cli = stub_cli("1.0.128 (Claude Code)");
+        match check_claude_version(&cli) {
+            Err(AgentError::UnsupportedCLIVersion { found, minimum, .. }) => {
+                assert_eq!(found, "1.0.128 (Claude Code)");
+                assert_eq!(minimum, "2.0.0");
+            }
+            other => panic!("expected UnsupportedCLIVersion, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unparseable_cli_version() {
+        let cli = stub_cli("not a version");
+        assert!(matches!(
+            check_claude_version(&cli),
+            Err(AgentError::UnsupportedCLIVersion { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_cli_version_times_out() {
+        // `--version` prints nothing and hangs
+        let cli = stub_cli_script("'; exec sleep 30; echo '", "exit 0");
+        let started = std::time::Instant::now();
+        match check_claude_version_with_timeout(&cli, Duration::from_millis(200)) {
+            Err(AgentError::ProcessError(message)) => {
+                assert!(message.contains("did not finish"), "{}", message)
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stderr_lines_reach_callback() {
+        let cli = stub_cli_script(
+            "2.0.0 (Claude Code)",
+            "echo 'first' >&2; echo '[ERROR] second' >&2",
+        );
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink = captured.clone();
+        let options = ClaudeAgentOptions::new()
+            .cli_path(cli)
+            .stderr(move |line| sink.lock().unwrap().push(line.to_string()));
+
+        let transport = SubprocessCLITransport::from_options(&options).unwrap();
+        assert!(transport.receive().await.is_err());
+
+        assert_eq!(*captured.lock().unwrap(), vec!["first", "[ERROR] second"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_receive_skips_non_json_lines() {
+        let cli = stub_cli_script(
+            "2.0.0 (Claude Code)",
+            "echo 'npm WARN something'; echo; echo '{\"type\":\"system\",\"subtype\":\"init\"}'; echo 'done.'",
+        );
+        let transport =
+            SubprocessCLITransport::from_options(&ClaudeAgentOptions::new().cli_path(cli)).unwrap();
+
+        let frame = transport.receive().await.unwrap();
+        assert_eq!(frame["subtype"], "init");
+        // Only the end of output is an error
+        let error = transport.receive().await.unwrap_err().to_string();
+        assert!(error.contains("Process ended"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stderr_ring_buffer_keeps_recent_lines() {
+        let cli = stub_cli_script(
+            "2.0.0 (Claude Code)",
+            "for i in 1 2 3 4 5; do echo \"line $i\" >&2; done; exit 3",
+        );
+        let options = ClaudeAgentOptions::new()
+            .cli_path(cli)
+            .stderr_buffer_lines(2);
+
+        let transport = SubprocessCLITransport::from_options(&options).unwrap();
+        let error = transport.receive().await.unwrap_err().to_string();
+
+        assert!(error.contains("line 4\nline 5"));
+        assert!(!error.contains("line 3"));
+        assert_eq!(transport.recent_stderr(), vec!["line 4", "line 5"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_close_waits_for_graceful_exit() {
+        let cli = stub_cli_script("2.0.0 (Claude Code)", "cat > /dev/null; exit 7");
+        let transport =
+            SubprocessCLITransport::from_options(&ClaudeAgentOptions::new().cli_path(cli)).unwrap();
+        assert!(transport.exit_status().await.is_none());
+
+        let status = transport
+            .close_with_timeout(Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(status.code(), Some(7));
+        assert_eq!(transport.exit_status().await.unwrap().code(), Some(7));
+        assert!(transport.send(serde_json::json!({})).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_close_kills_cli_that_ignores_eof() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let cli = stub_cli_script("2.0.0 (Claude Code)", "exec sleep 30");
+        let transport =
+            SubprocessCLITransport::from_options(&ClaudeAgentOptions::new().cli_path(cli)).unwrap();
+
+        let status = transport
+            .close_with_timeout(Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(status.code(), None);
+        assert_eq!(status.signal(), Some(9)); // SIGKILL
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_close_kills_processes_started_by_cli() {
+        let cli = stub_cli_script(
+            "2.0.0 (Claude Code)",
+            "sleep 30 & echo $! >&2; cat > /dev/null",
+        );
+        let transport =
+            SubprocessCLITransport::from_options(&ClaudeAgentOptions::new().cli_path(cli)).unwrap();
+        let tool_pid = wait_for_stderr_line(&transport).await;
+
+        transport.close().await.unwrap();
+        assert!(wait_until_gone(&tool_pid).await);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_drop_kills_process_group() {
+        let cli = stub_cli_script(
+            "2.0.0 (Claude Code)",
+            "sleep 30 & echo $! >&2; cat > /dev/null",
+        );
+        let transport =
+            SubprocessCLITransport::from_options(&ClaudeAgentOptions::new().cli_path(cli)).unwrap();
+        let tool_pid = wait_for_stderr_line(&transport).await;
+
+        drop(transport);
+        assert!(wait_until_gone(&tool_pid).await);
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn wait_for_stderr_line(transport: &SubprocessCLITransport) -> String {
+        for _ in 0..100 {
+            if let Some(line) = transport.recent_stderr().into_iter().next() {
+                return line;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("stub CLI never wrote to stderr");
+    }
 }
@@ -0,0 +1,201 @@
+use claude_agent_sdk::message_parser::parse_message;
+use claude_agent_sdk::usage::{ModelUsage, ServerToolUse, Usage, UsageTracker};
+use serde_json::{json, Value};
+
+fn assistant_frame(id: &str, input_tokens: u64, output_tokens: u64) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "id": id,
+            "model": "claude-sonnet-4-5",
+            "content": [{"type": "text", "text": "..."}],
+            "usage": {
+                "input_tokens": input_tokens,
+                "output_tokens": output_tokens,
+                "cache_read_input_tokens": 100,
+                "service_tier": "standard"
+            }
+        },
+        "session_id": "s1"
+    })
+}
+
+fn result_frame(session_id: &str, cost: f64) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1000,
+        "duration_api_ms": 800,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": session_id,
+        "total_cost_usd": cost,
+        "usage": {
+            "input_tokens": 10,
+            "output_tokens": 20,
+            "cache_creation_input_tokens": 5,
+            "cache_read_input_tokens": 100,
+            "server_tool_use": {"web_search_requests": 1, "web_fetch_requests": 0}
+        },
+        "modelUsage": {
+            "claude-sonnet-4-5": {
+                "inputTokens": 8,
+                "outputTokens": 15,
+                "cacheReadInputTokens": 100,
+                "cacheCreationInputTokens": 5,
+                "webSearchRequests": 1,
+                "costUSD": cost * 0.75,
+                "contextWindow": 200000
+            },
+            "claude-haiku-4-5": {
+                "inputTokens": 2,
+                "outputTokens": 5,
+                "costUSD": cost * 0.25,
+                "contextWindow": 200000
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_arithmetic() {
+        let a = Usage {
+            input_tokens: 1,
+            output_tokens: 2,
+            cache_creation_input_tokens: 3,
+            cache_read_input_tokens: 4,
+            server_tool_use: ServerToolUse {
+                web_search_requests: 1,
+                web_fetch_requests: 0,
+            },
+        };
+        let total: Usage = [a, a, Usage::default()].iter().sum();
+        assert_eq!(total, a + a);
+        assert_eq!(total.input_tokens, 2);
+        assert_eq!(total.server_tool_use.web_search_requests, 2);
+        assert_eq!(a.total_input_tokens(), 8);
+        assert_eq!(a.total_tokens(), 10);
+
+        let mut model = ModelUsage {
+            cost_usd: 0.5,
+            context_window: 200_000,
+            ..ModelUsage::default()
+        };
+        model += ModelUsage {
+            cost_usd: 0.25,
+            context_window: 1_000_000,
+            ..ModelUsage::default()
+        };
+        assert_eq!(model.cost_usd, 0.75);
+        assert_eq!(model.context_window, 1_000_000);
+    }
+
+    #[test]
+    fn test_assistant_usage_is_parsed() {
+        let Ok(claude_agent_sdk::Message::Assistant(assistant)) =
+            parse_message(&assistant_frame("msg_1", 3, 7))
+        else {
+            panic!("Expected assistant message");
+        };
+        assert_eq!(assistant.id.as_deref(), Some("msg_1"));
+        let usage = assistant.usage.unwrap();
+        assert_eq!(usage.input_tokens, 3);
+        assert_eq!(usage.output_tokens, 7);
+        assert_eq!(usage.cache_read_input_tokens, 100);
+    }
+
+    #[test]
+    fn test_tracker_counts_each_api_message_once() {
+        let mut tracker = UsageTracker::new();
+        // A response with two content blocks arrives as two messages with one id
+        tracker.record(&parse_message(&assistant_frame("msg_1", 3, 7)).unwrap());
+        tracker.record(&parse_message(&assistant_frame("msg_1", 3, 7)).unwrap());
+        tracker.record(&parse_message(&assistant_frame("msg_2", 4, 1)).unwrap());
+
+        let total = tracker.total();
+        assert_eq!(total.input_tokens, 7);
+        assert_eq!(total.output_tokens, 8);
+        assert_eq!(total.cache_read_input_tokens, 200);
+        assert!(tracker.sessions().is_empty());
+    }
+
+    #[test]
+    fn test_tracker_totals_results_across_sessions() {
+        let mut tracker = UsageTracker::new();
+        tracker.record(&parse_message(&assistant_frame("msg_1", 3, 7)).unwrap());
+        tracker.record(&parse_message(&result_frame("s1", 0.02)).unwrap());
+        // The second turn of s1 reports the session's cost and model usage so far
+        let mut second_turn = result_frame("s1", 0.05);
+        second_turn["modelUsage"]["claude-sonnet-4-5"]["inputTokens"] = json!(16);
+        second_turn["modelUsage"]["claude-haiku-4-5"]["inputTokens"] = json!(4);
+        tracker.record(&parse_message(&second_turn).unwrap());
+        tracker.record(&parse_message(&result_frame("s2", 0.04)).unwrap());
+
+        // The result replaces the running estimate from the assistant message
+        let total = tracker.total();
+        assert_eq!(total.input_tokens, 30);
+        assert_eq!(total.output_tokens, 60);
+        assert_eq!(total.server_tool_use.web_search_requests, 3);
+        assert!((tracker.total_cost_usd() - 0.09).abs() < 1e-9);
+
+        let s1 = tracker.session("s1").unwrap();
+        assert_eq!(s1.num_turns, 2);
+        assert_eq!(s1.usage.output_tokens, 40);
+        assert!((s1.cost_usd - 0.05).abs() < 1e-9);
+        assert_eq!(s1.by_model["claude-haiku-4-5"].input_tokens, 4);
+
+        let by_model = tracker.by_model();
+        assert_eq!(by_model["claude-sonnet-4-5"].input_tokens, 24);
+        assert_eq!(
+            by_model["claude-sonnet-4-5"]
+                .usage()
+                .cache_read_input_tokens,
+            200
+        );
+        assert!((by_model["claude-haiku-4-5"].cost_usd - 0.0225).abs() < 1e-9);
+        assert!(tracker.session("s3").is_none());
+    }
+
+    #[test]
+    fn test_null_usage_fields_are_zero() {
+        let mut assistant = assistant_frame("msg_1", 3, 7);
+        assistant["message"]["usage"]["cache_read_input_tokens"] = Value::Null;
+        assistant["message"]["usage"]["cache_creation_input_tokens"] = Value::Null;
+        assistant["message"]["usage"]["server_tool_use"] = Value::Null;
+        let Ok(claude_agent_sdk::Message::Assistant(assistant)) = parse_message(&assistant) else {
+            panic!("Expected assistant message");
+        };
+        let usage = assistant.usage.unwrap();
+        assert_eq!(usage.input_tokens, 3);
+        assert_eq!(usage.cache_read_input_tokens, 0);
+        assert_eq!(usage.server_tool_use, ServerToolUse::default());
+
+        let mut result = result_frame("s1", 0.02);
+        result["usage"]["server_tool_use"] = Value::Null;
+        result["usage"]["cache_creation_input_tokens"] = Value::Null;
+        result["modelUsage"]["claude-haiku-4-5"]["cacheReadInputTokens"] = Value::Null;
+        let Ok(claude_agent_sdk::Message::Result(result)) = parse_message(&result) else {
+            panic!("Expected result message");
+        };
+        let usage = result.usage.unwrap();
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(usage.server_tool_use.web_search_requests, 0);
+        assert_eq!(
+            result.model_usage.unwrap()["claude-haiku-4-5"].cache_read_input_tokens,
+            0
+        );
+    }
+
+    #[test]
+    fn test_invalid_usage_error_carries_whole_frame() {
+        let mut frame = assistant_frame("msg_1", 3, 7);
+        frame["message"]["usage"]["input_tokens"] = json!("many");
+        let err = parse_message(&frame).unwrap_err();
+        assert_eq!(err.raw, frame);
+        assert!(err.reason.contains("usage"), "{}", err.reason);
+    }
+}